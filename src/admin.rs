@@ -0,0 +1,194 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    error::Error,
+    net::SocketAddr,
+    str::FromStr,
+    sync::Arc,
+};
+
+use ethers_core::types::{H160, H256, U64};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use mev_share_rpc_api::SendBundleRequest;
+use serde::Serialize;
+use serde_json::json;
+use tokio::{sync::RwLock, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{
+    constants::BUNDLE_BLOCK_WINDOW, context::AppContext, executor::Executor, handler::send_checked,
+};
+
+/// A bundle accepted by the relay whose inclusion window has not passed yet.
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightBundle {
+    pub strategy: Option<&'static str>,
+    pub contract: H160,
+    /// Pending txs backrun, nested bundles excluded.
+    pub backruns: Vec<H256>,
+    pub relay: String,
+    pub block: U64,
+    pub max_block: U64,
+    pub request: SendBundleRequest,
+}
+
+/// Runtime controls exposed through the admin API.
+#[derive(Debug, Clone, Default)]
+pub struct AdminState {
+    pub paused: Arc<RwLock<HashSet<String>>>,
+    pub in_flight: Arc<RwLock<HashMap<H256, InFlightBundle>>>,
+}
+
+impl AdminState {
+    pub async fn is_paused(&self, strategy: &str) -> bool {
+        self.paused.read().await.contains(strategy)
+    }
+
+    pub async fn set_paused(&self, strategy: &str, paused: bool) {
+        if paused {
+            self.paused.write().await.insert(strategy.to_string());
+        } else {
+            self.paused.write().await.remove(strategy);
+        }
+        info!(strategy, paused, "Strategy toggled");
+    }
+
+    pub async fn track(&self, bundle_hash: H256, bundle: InFlightBundle) {
+        self.in_flight.write().await.insert(bundle_hash, bundle);
+    }
+
+    /// Forgets bundles whose window ended before `block` or whose contract got captured.
+    pub async fn prune(&self, block: U64, completed: &HashSet<H160>) {
+        self.in_flight
+            .write()
+            .await
+            .retain(|_, b| b.max_block >= block && !completed.contains(&b.contract));
+    }
+}
+
+/// Resends an in-flight bundle targeting the next blocks, through the same checks as new
+/// bundles, tracking it under its new hash.
+pub async fn resubmit(
+    ctx: &AppContext,
+    bundle_hash: H256,
+) -> Result<H256, Box<dyn Error + Send + Sync>> {
+    let mut bundle = ctx
+        .admin
+        .in_flight
+        .read()
+        .await
+        .get(&bundle_hash)
+        .cloned()
+        .ok_or("Unknown bundle")?;
+    let block = ctx.progress.get_latest_block().await;
+    bundle.block = block;
+    bundle.max_block = block + BUNDLE_BLOCK_WINDOW;
+    bundle.request.inclusion.block = bundle.block;
+    bundle.request.inclusion.max_block = Some(bundle.max_block);
+
+    let resp = send_checked(ctx, &bundle.request).await?;
+    info!(old = ?bundle_hash, new = ?resp.bundle_hash, "Resubmitted bundle");
+    let mut in_flight = ctx.admin.in_flight.write().await;
+    in_flight.remove(&bundle_hash);
+    in_flight.insert(resp.bundle_hash, bundle);
+    Ok(resp.bundle_hash)
+}
+
+/// Serves the admin API:
+///
+/// - `GET /progress`: completed contracts per wallet
+/// - `GET /gas`: gas paid in wei per wallet and contract
+/// - `GET /bundles`: in-flight bundles by bundle hash
+/// - `GET /block`: latest block
+/// - `GET /strategies`: strategies and whether they are paused
+/// - `POST /strategies/<name>/pause` and `POST /strategies/<name>/resume`
+/// - `POST /bundles/<hash>/resubmit`
+pub fn serve_admin(
+    ctx: Arc<AppContext>,
+    addr: SocketAddr,
+) -> Result<JoinHandle<()>, Box<dyn Error + Send + Sync>> {
+    let make_service = make_service_fn(move |_| {
+        let ctx = ctx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let ctx = ctx.clone();
+                async move { Ok::<_, Infallible>(route(&ctx, req).await) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    info!(addr = %server.local_addr(), "Serving admin API");
+    Ok(tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Admin server failed: {:?}", e);
+        }
+    }))
+}
+
+async fn route(ctx: &AppContext, req: Request<Body>) -> Response<Body> {
+    let path = req
+        .uri()
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+    match (req.method(), path.as_slice()) {
+        (&Method::GET, ["progress"]) => respond(StatusCode::OK, &*ctx.progress.inner.read().await),
+        (&Method::GET, ["gas"]) => respond(StatusCode::OK, &*ctx.progress.gas_paid.read().await),
+        (&Method::GET, ["bundles"]) => respond(StatusCode::OK, &*ctx.admin.in_flight.read().await),
+        (&Method::GET, ["block"]) => respond(
+            StatusCode::OK,
+            &json!({ "latest_block": ctx.progress.get_latest_block().await }),
+        ),
+        (&Method::GET, ["strategies"]) => {
+            let paused = ctx.admin.paused.read().await;
+            let strategies = Executor::strategy_names()
+                .into_iter()
+                .map(|name| (name, paused.contains(name)))
+                .collect::<HashMap<_, _>>();
+            respond(StatusCode::OK, &strategies)
+        }
+        (&Method::POST, ["strategies", name, action @ ("pause" | "resume")]) => {
+            if !Executor::strategy_names().contains(name) {
+                return error_response(StatusCode::NOT_FOUND, "Unknown strategy");
+            }
+            ctx.admin.set_paused(name, *action == "pause").await;
+            respond(
+                StatusCode::OK,
+                &json!({ "strategy": name, "paused": *action == "pause" }),
+            )
+        }
+        (&Method::POST, ["bundles", hash, "resubmit"]) => {
+            let Ok(hash) = H256::from_str(hash) else {
+                return error_response(StatusCode::BAD_REQUEST, "Invalid bundle hash");
+            };
+            match resubmit(ctx, hash).await {
+                Ok(bundle_hash) => respond(StatusCode::OK, &json!({ "bundle_hash": bundle_hash })),
+                Err(e) => {
+                    warn!(bundle = ?hash, "Resubmit failed: {:?}", e);
+                    error_response(StatusCode::BAD_GATEWAY, &e.to_string())
+                }
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+fn respond<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(body) => Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    respond(status, &json!({ "error": message }))
+}
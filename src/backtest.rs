@@ -0,0 +1,153 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+};
+
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, Block, Transaction, H160, H256, U64},
+    utils::rlp::Rlp,
+};
+use futures_util::Future;
+use mev_share_rpc_api::{BundleItem, SendBundleRequest};
+
+use crate::{context::AppContext, error::DecodeError};
+
+tokio::task_local! {
+    /// Name of the strategy whose handler is running in the current task.
+    static STRATEGY: &'static str;
+}
+
+pub fn current_strategy() -> Option<&'static str> {
+    STRATEGY.try_with(|s| *s).ok()
+}
+
+pub async fn with_strategy<F: Future>(strategy: &'static str, fut: F) -> F::Output {
+    STRATEGY.scope(strategy, fut).await
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct StrategyStats {
+    pub matched: usize,
+    pub built: usize,
+    /// Log hints the strategy matched but could not decode.
+    pub decode_failed: usize,
+    /// Other handler errors before a bundle could be sent, e.g. RPC errors or refused bundles.
+    pub failed: usize,
+    /// Bundles whose backrun targets all landed within the inclusion window.
+    pub landable: usize,
+    /// Bundles for which one of our txs to the same contract actually landed in the window.
+    pub landed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuiltBundle {
+    pub strategy: &'static str,
+    pub hashes: Vec<H256>,
+    pub recipients: Vec<H160>,
+    pub block: U64,
+    pub max_block: U64,
+}
+
+#[derive(Debug, Default)]
+pub struct Backtest {
+    pub stats: HashMap<&'static str, StrategyStats>,
+    pub bundles: Vec<BuiltBundle>,
+}
+
+/// Starts collecting backtest results in the context, until `finish_backtest` is called.
+pub fn start_backtest(ctx: &AppContext) {
+    *ctx.backtest.lock().unwrap() = Some(Backtest::default());
+}
+
+pub fn report_match(ctx: &AppContext, strategy: &'static str) {
+    if let Some(backtest) = ctx.backtest.lock().unwrap().as_mut() {
+        backtest.stats.entry(strategy).or_default().matched += 1;
+    }
+}
+
+pub fn report_bundle(ctx: &AppContext, bundle: &SendBundleRequest) {
+    let Some(strategy) = current_strategy() else {
+        return;
+    };
+    if let Some(backtest) = ctx.backtest.lock().unwrap().as_mut() {
+        backtest.stats.entry(strategy).or_default().built += 1;
+
+        let mut hashes = vec![];
+        let mut recipients = vec![];
+        for item in &bundle.bundle_body {
+            match item {
+                BundleItem::Hash { hash } => hashes.push(*hash),
+                BundleItem::Tx { tx, .. } => {
+                    if let Ok((tx, _)) = TypedTransaction::decode_signed(&Rlp::new(tx.as_ref())) {
+                        recipients.extend(tx.to_addr().copied());
+                    }
+                }
+                _ => {}
+            }
+        }
+        backtest.bundles.push(BuiltBundle {
+            strategy,
+            hashes,
+            recipients,
+            block: bundle.inclusion.block,
+            max_block: bundle.inclusion.max_block.unwrap_or(bundle.inclusion.block),
+        });
+    }
+}
+
+pub fn report_failure(ctx: &AppContext, err: &(dyn Error + Send + Sync + 'static)) {
+    let Some(strategy) = current_strategy() else {
+        return;
+    };
+    if let Some(backtest) = ctx.backtest.lock().unwrap().as_mut() {
+        let stats = backtest.stats.entry(strategy).or_default();
+        if err.downcast_ref::<DecodeError>().is_some() {
+            stats.decode_failed += 1;
+        } else {
+            stats.failed += 1;
+        }
+    }
+}
+
+/// Scores the bundles built so far against the recorded blocks and returns the stats of every
+/// strategy, including those that never matched.
+pub fn finish_backtest(
+    ctx: &AppContext,
+    strategies: &[&'static str],
+    blocks: &[Block<Transaction>],
+) -> BTreeMap<&'static str, StrategyStats> {
+    let wallet = ctx.address();
+    let Backtest { stats, bundles } = ctx.backtest.lock().unwrap().take().unwrap_or_default();
+    let mut stats = stats.into_iter().collect::<BTreeMap<_, _>>();
+    for strategy in strategies {
+        stats.entry(*strategy).or_default();
+    }
+
+    for bundle in bundles {
+        let window = blocks
+            .iter()
+            .filter(|b| {
+                b.number
+                    .map(|n| n >= bundle.block && n <= bundle.max_block)
+                    .unwrap_or_default()
+            })
+            .flat_map(|b| b.transactions.iter())
+            .collect::<Vec<_>>();
+        let included = window.iter().map(|tx| tx.hash).collect::<HashSet<_>>();
+
+        let entry = stats.entry(bundle.strategy).or_default();
+        if bundle.hashes.iter().all(|h| included.contains(h)) {
+            entry.landable += 1;
+        }
+        if window.iter().any(|tx| {
+            tx.from == wallet
+                && tx
+                    .to
+                    .map(|to| bundle.recipients.contains(&to))
+                    .unwrap_or_default()
+        }) {
+            entry.landed += 1;
+        }
+    }
+    stats
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, U256},
+    utils::{format_ether, rlp::Rlp},
+};
+use mev_share_rpc_api::BundleItem;
+use tracing::{error, info};
+
+use crate::{
+    config::BudgetConfig,
+    constants::{GAS_LIMIT, GWEI, MAX_GAS_PRICE, TIP},
+};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// The most a tx signed by `sign_transaction` can cost, all of its gas at the max fee.
+pub fn max_tx_cost() -> U256 {
+    U256::from(GAS_LIMIT) * U256::from(MAX_GAS_PRICE * GWEI + TIP)
+}
+
+/// Nonces of the txs of a bundle, nested ones included, without duplicates. Of several txs
+/// sharing a nonce at most one can land, so each nonce costs `max_tx_cost` once.
+pub fn bundle_nonces(items: &[BundleItem]) -> Result<Vec<U256>, Box<dyn Error + Send + Sync>> {
+    let mut nonces = vec![];
+    for item in items {
+        match item {
+            BundleItem::Tx { tx, .. } => {
+                let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(tx.as_ref()))?;
+                nonces.push(tx.nonce().copied().unwrap_or_default());
+            }
+            BundleItem::Bundle { bundle } => nonces.extend(bundle_nonces(&bundle.bundle_body)?),
+            _ => {}
+        }
+    }
+    nonces.sort();
+    nonces.dedup();
+    Ok(nonces)
+}
+
+struct Spend {
+    at: Instant,
+    nonce: U256,
+    /// Accepted bundles using the nonce, it is given back once none is left.
+    bundles: usize,
+}
+
+/// Wallet balance, as of the latest block, and the spend limits bundles are checked against.
+pub struct Budget {
+    config: BudgetConfig,
+    balance: Mutex<Option<U256>>,
+    /// Nonces of the bundles sent in the last hour, oldest first, each costing `max_tx_cost`.
+    spent: Mutex<VecDeque<Spend>>,
+}
+
+impl Budget {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            balance: Mutex::new(None),
+            spent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// A budget refusing nothing, for backtests where nothing is spent.
+    pub fn unlimited() -> Self {
+        Self::new(BudgetConfig {
+            floor: U256::zero(),
+            max_per_bundle: U256::MAX,
+            max_per_hour: U256::MAX,
+        })
+    }
+
+    pub fn balance(&self) -> Option<U256> {
+        *self.balance.lock().unwrap()
+    }
+
+    /// Records the balance of a new block, alerting when it crosses the floor.
+    pub fn set_balance(&self, balance: U256) {
+        let previous = self.balance.lock().unwrap().replace(balance);
+        let floor = self.config.floor;
+        let was_below = previous.map(|b| b < floor).unwrap_or_default();
+        if balance < floor && !was_below {
+            error!(
+                balance = %format_ether(balance),
+                floor = %format_ether(floor),
+                "Wallet balance below floor, refusing to build bundles until it is topped up"
+            );
+        } else if balance >= floor && was_below {
+            info!(balance = %format_ether(balance), "Wallet balance back above floor");
+        }
+    }
+
+    /// Fails if the balance is below the floor. Not enforced until the first block's balance is
+    /// known.
+    pub fn check_balance(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self.balance() {
+            Some(balance) if balance < self.config.floor => Err(format!(
+                "Wallet balance {} ETH below floor of {} ETH",
+                format_ether(balance),
+                format_ether(self.config.floor)
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the nonces not already spent in the last hour against the hourly budget, unless
+    /// the bundle is over the per-bundle or hourly max spend or costs more than the balance left
+    /// above the floor.
+    pub fn reserve(&self, nonces: &[U256]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.reserve_at(nonces, Instant::now())
+    }
+
+    fn reserve_at(
+        &self,
+        nonces: &[U256],
+        now: Instant,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_balance()?;
+        let cost = max_tx_cost() * U256::from(nonces.len());
+        if cost > self.config.max_per_bundle {
+            return Err(format!(
+                "Bundle costing up to {} ETH over the per bundle max of {} ETH",
+                format_ether(cost),
+                format_ether(self.config.max_per_bundle)
+            )
+            .into());
+        }
+        if let Some(balance) = self.balance() {
+            if balance < self.config.floor + cost {
+                return Err(format!(
+                    "Bundle costing up to {} ETH would take the balance of {} ETH below the floor",
+                    format_ether(cost),
+                    format_ether(balance)
+                )
+                .into());
+            }
+        }
+
+        let mut spent = self.spent.lock().unwrap();
+        while spent
+            .front()
+            .map(|s| now.duration_since(s.at) > HOUR)
+            .unwrap_or_default()
+        {
+            spent.pop_front();
+        }
+        let new = nonces
+            .iter()
+            .filter(|nonce| !spent.iter().any(|s| s.nonce == **nonce))
+            .count();
+        let hourly = max_tx_cost() * U256::from(spent.len());
+        if hourly + max_tx_cost() * U256::from(new) > self.config.max_per_hour {
+            return Err(format!(
+                "Bundle costing up to {} ETH over the hourly max of {} ETH, {} ETH spent",
+                format_ether(cost),
+                format_ether(self.config.max_per_hour),
+                format_ether(hourly)
+            )
+            .into());
+        }
+        for nonce in nonces {
+            match spent.iter_mut().find(|s| s.nonce == *nonce) {
+                Some(s) => s.bundles += 1,
+                None => spent.push_back(Spend {
+                    at: now,
+                    nonce: *nonce,
+                    bundles: 1,
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Gives back a reservation, for a bundle the relay did not accept.
+    pub fn release(&self, nonces: &[U256]) {
+        let mut spent = self.spent.lock().unwrap();
+        for nonce in nonces {
+            if let Some(s) = spent.iter_mut().find(|s| s.nonce == *nonce) {
+                s.bundles -= 1;
+            }
+        }
+        spent.retain(|s| s.bundles > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::{Eip1559TransactionRequest, H160};
+    use ethers_signers::{LocalWallet, Signer};
+    use mev_share_rpc_api::SendBundleRequest;
+
+    use super::*;
+    use crate::signer::sign_transaction;
+
+    fn budget(txs_per_hour: u64) -> Budget {
+        Budget::new(BudgetConfig {
+            floor: U256::exp10(17),
+            max_per_bundle: U256::MAX,
+            max_per_hour: max_tx_cost() * U256::from(txs_per_hour),
+        })
+    }
+
+    async fn tx(nonce: u64) -> BundleItem {
+        let wallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(5u64);
+        let tx = Eip1559TransactionRequest::new()
+            .to(H160::from_low_u64_be(1))
+            .nonce(nonce);
+        BundleItem::Tx {
+            tx: sign_transaction(&wallet, tx).await.unwrap(),
+            can_revert: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn counts_each_nonce_once() {
+        // magic number candidates are separate bundles sharing one nonce
+        let candidates = vec![tx(3).await, tx(3).await];
+        assert_eq!(bundle_nonces(&candidates).unwrap(), vec![U256::from(3)]);
+
+        let nested = BundleItem::Bundle {
+            bundle: SendBundleRequest {
+                bundle_body: vec![tx(3).await, tx(4).await],
+                ..Default::default()
+            },
+        };
+        assert_eq!(
+            bundle_nonces(&[tx(4).await, nested]).unwrap(),
+            vec![U256::from(3), U256::from(4)]
+        );
+
+        let budget = budget(1);
+        assert!(budget.reserve(&[U256::from(3)]).is_ok());
+        assert!(budget.reserve(&[U256::from(3)]).is_ok());
+        assert!(budget.reserve(&[U256::from(4)]).is_err());
+    }
+
+    #[test]
+    fn releases_rejected_bundles() {
+        let budget = budget(1);
+        budget.reserve(&[U256::from(1)]).unwrap();
+        budget.reserve(&[U256::from(1)]).unwrap();
+
+        // the nonce stays spent while another bundle using it was accepted
+        budget.release(&[U256::from(1)]);
+        assert!(budget.reserve(&[U256::from(2)]).is_err());
+        budget.release(&[U256::from(1)]);
+        assert!(budget.reserve(&[U256::from(2)]).is_ok());
+    }
+
+    #[test]
+    fn forgets_spends_after_an_hour() {
+        let budget = budget(1);
+        let now = Instant::now();
+        budget.reserve_at(&[U256::from(1)], now).unwrap();
+        assert!(budget.reserve_at(&[U256::from(2)], now + HOUR).is_err());
+        assert!(budget
+            .reserve_at(&[U256::from(2)], now + HOUR + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn keeps_the_floor_plus_cost() {
+        let budget = budget(10);
+        let floor = U256::exp10(17);
+        let cost = max_tx_cost() * U256::from(2);
+
+        budget.set_balance(floor + cost - U256::one());
+        assert!(budget.check_balance().is_ok());
+        assert!(budget.reserve(&[U256::from(1), U256::from(2)]).is_err());
+
+        budget.set_balance(floor + cost);
+        assert!(budget.reserve(&[U256::from(1), U256::from(2)]).is_ok());
+
+        budget.set_balance(floor - U256::one());
+        assert!(budget.check_balance().is_err());
+        assert!(budget.reserve(&[U256::from(3)]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::HashSet, error::Error};
+
+use ethers_core::types::{Bytes, H256, U64};
+use mev_share_rpc_api::{BundleItem, Inclusion, SendBundleRequest};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Builds a bundle backrunning one or more pending MEV-Share txs and nested bundles with our
+/// txs, checking on `build` that it is ordered the way MEV-Share accepts it.
+#[derive(Debug, Clone, Default)]
+pub struct BundleBuilder {
+    body: Vec<BundleItem>,
+}
+
+impl BundleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Backruns a pending tx, only known by its hash.
+    pub fn backrun(mut self, hash: H256) -> Self {
+        self.body.push(BundleItem::Hash { hash });
+        self
+    }
+
+    /// Backruns an existing bundle, e.g. one sent earlier by another strategy.
+    pub fn nest(mut self, bundle: SendBundleRequest) -> Self {
+        self.body.push(BundleItem::Bundle { bundle });
+        self
+    }
+
+    /// Appends one of our signed txs.
+    pub fn tx(mut self, tx: Bytes, can_revert: bool) -> Self {
+        self.body.push(BundleItem::Tx { tx, can_revert });
+        self
+    }
+
+    pub fn items(mut self, items: impl IntoIterator<Item = BundleItem>) -> Self {
+        self.body.extend(items);
+        self
+    }
+
+    /// Hashes of the pending txs backrun so far, nested bundles excluded.
+    pub fn targets(&self) -> Vec<H256> {
+        self.body
+            .iter()
+            .filter_map(|item| match item {
+                BundleItem::Hash { hash } => Some(*hash),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Checks the bundle and returns it targeting `inclusion`:
+    ///
+    /// - Pending txs and nested bundles all come before our txs, which can only backrun them.
+    /// - No pending tx is backrun twice, nested bundles included.
+    /// - There is at least one of our txs, and not all of them can revert, or the bundle could
+    ///   land without doing anything.
+    /// - Nested bundles are not empty, ordered the same way and valid for every block of
+    ///   `inclusion`.
+    pub fn build(self, inclusion: Inclusion) -> Result<SendBundleRequest, BoxError> {
+        check_body(&self.body, &inclusion, &mut HashSet::new())?;
+        let txs = self
+            .body
+            .iter()
+            .filter_map(|item| match item {
+                BundleItem::Tx { can_revert, .. } => Some(*can_revert),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if txs.is_empty() {
+            return Err("Bundle without any tx of ours".into());
+        }
+        if txs.iter().all(|can_revert| *can_revert) {
+            return Err("Every tx of ours in the bundle can revert".into());
+        }
+
+        Ok(SendBundleRequest {
+            bundle_body: self.body,
+            inclusion,
+            ..Default::default()
+        })
+    }
+}
+
+fn check_body(
+    body: &[BundleItem],
+    inclusion: &Inclusion,
+    hashes: &mut HashSet<H256>,
+) -> Result<(), BoxError> {
+    if body.is_empty() {
+        return Err("Empty bundle body".into());
+    }
+    let mut seen_tx = false;
+    for (i, item) in body.iter().enumerate() {
+        match item {
+            BundleItem::Tx { .. } => seen_tx = true,
+            _ if seen_tx => {
+                return Err(format!("Body index {} backruns after one of our txs", i).into())
+            }
+            BundleItem::Hash { hash } => {
+                if !hashes.insert(*hash) {
+                    return Err(format!("Pending tx {:?} backrun twice", hash).into());
+                }
+            }
+            BundleItem::Bundle { bundle } => {
+                if bundle.inclusion.block > inclusion.block
+                    || max_block(&bundle.inclusion) < max_block(inclusion)
+                {
+                    return Err(format!(
+                        "Nested bundle at body index {} not valid for every target block",
+                        i
+                    )
+                    .into());
+                }
+                check_body(&bundle.bundle_body, inclusion, hashes)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn max_block(inclusion: &Inclusion) -> U64 {
+    inclusion.max_block.unwrap_or(inclusion.block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inclusion(block: u64, max_block: u64) -> Inclusion {
+        Inclusion {
+            block: block.into(),
+            max_block: Some(max_block.into()),
+        }
+    }
+
+    fn tx(n: u8) -> Bytes {
+        Bytes::from(vec![n])
+    }
+
+    #[test]
+    fn builds_backruns_before_our_txs() {
+        let (first, second) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let builder = BundleBuilder::new()
+            .backrun(first)
+            .backrun(second)
+            .tx(tx(1), false)
+            .tx(tx(2), true);
+        assert_eq!(builder.targets(), vec![first, second]);
+
+        let bundle = builder.build(inclusion(10, 12)).unwrap();
+        assert_eq!(bundle.bundle_body.len(), 4);
+        assert_eq!(bundle.inclusion.block, U64::from(10));
+    }
+
+    #[test]
+    fn rejects_backruns_after_our_txs() {
+        let res = BundleBuilder::new()
+            .tx(tx(1), false)
+            .backrun(H256::from_low_u64_be(1))
+            .build(inclusion(10, 12));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn rejects_txs_backrun_twice() {
+        let hash = H256::from_low_u64_be(1);
+        let res = BundleBuilder::new()
+            .backrun(hash)
+            .backrun(hash)
+            .tx(tx(1), false)
+            .build(inclusion(10, 12));
+        assert!(res.is_err());
+
+        let nested = BundleBuilder::new()
+            .backrun(hash)
+            .tx(tx(1), false)
+            .build(inclusion(10, 12))
+            .unwrap();
+        let res = BundleBuilder::new()
+            .nest(nested)
+            .backrun(hash)
+            .tx(tx(2), false)
+            .build(inclusion(10, 12));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn needs_a_tx_that_cannot_revert() {
+        let hash = H256::from_low_u64_be(1);
+        assert!(BundleBuilder::new()
+            .backrun(hash)
+            .build(inclusion(10, 12))
+            .is_err());
+        assert!(BundleBuilder::new()
+            .backrun(hash)
+            .tx(tx(1), true)
+            .tx(tx(2), true)
+            .build(inclusion(10, 12))
+            .is_err());
+    }
+
+    #[test]
+    fn nested_bundles_cover_every_target_block() {
+        let nested = |inclusion| {
+            BundleBuilder::new()
+                .backrun(H256::from_low_u64_be(1))
+                .tx(tx(1), false)
+                .build(inclusion)
+                .unwrap()
+        };
+        let build = |bundle| {
+            BundleBuilder::new()
+                .nest(bundle)
+                .tx(tx(2), false)
+                .build(inclusion(10, 12))
+        };
+        assert!(build(nested(inclusion(9, 12))).is_ok());
+        assert!(build(nested(inclusion(11, 12))).is_err());
+        assert!(build(nested(inclusion(10, 11))).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, error::Error, path::Path};
+
+use ethers_core::types::H160;
+use mev_share_rpc_api::{
+    BundleItem, Privacy, PrivacyHint, Refund, RefundConfig, SendBundleRequest, Validity,
+};
+use serde::Deserialize;
+
+/// Key of the options used by strategies without options of their own.
+pub const DEFAULT_OPTIONS: &str = "default";
+
+/// What the relay may share of a bundle with searchers.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hint {
+    Calldata,
+    ContractAddress,
+    Logs,
+    FunctionSelector,
+    Hash,
+    TxHash,
+}
+
+/// Percent of the MEV paid back to the sender of the tx at `body_idx`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundEntry {
+    pub body_idx: u64,
+    pub percent: u64,
+}
+
+/// Percent of our refund sent to `address`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundRecipient {
+    pub address: H160,
+    pub percent: u64,
+}
+
+/// Privacy and validity settings of the bundles built by one strategy, all unset by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BundleOptions {
+    /// Hints shared with searchers, none if empty.
+    pub hints: Option<Vec<Hint>>,
+    /// Builders the bundle is sent to, every builder the relay knows if unset.
+    pub builders: Option<Vec<String>>,
+    pub refund: Option<Vec<RefundEntry>>,
+    pub refund_config: Option<Vec<RefundRecipient>>,
+}
+
+impl BundleOptions {
+    pub fn privacy(&self) -> Option<Privacy> {
+        if self.hints.is_none() && self.builders.is_none() {
+            return None;
+        }
+        Some(Privacy {
+            hints: self.hints.as_ref().map(|hints| {
+                hints
+                    .iter()
+                    .fold(PrivacyHint::default(), |hint, h| match h {
+                        Hint::Calldata => hint.with_calldata(),
+                        Hint::ContractAddress => hint.with_contract_address(),
+                        Hint::Logs => hint.with_logs(),
+                        Hint::FunctionSelector => hint.with_function_selector(),
+                        Hint::Hash => hint.with_hash(),
+                        Hint::TxHash => hint.with_tx_hash(),
+                    })
+            }),
+            builders: self.builders.clone(),
+        })
+    }
+
+    pub fn validity(&self) -> Option<Validity> {
+        if self.refund.is_none() && self.refund_config.is_none() {
+            return None;
+        }
+        Some(Validity {
+            refund: self.refund.as_ref().map(|refund| {
+                refund
+                    .iter()
+                    .map(|r| Refund {
+                        body_idx: r.body_idx,
+                        percent: r.percent,
+                    })
+                    .collect()
+            }),
+            refund_config: self.refund_config.as_ref().map(|config| {
+                config
+                    .iter()
+                    .map(|r| RefundConfig {
+                        address: r.address,
+                        percent: r.percent,
+                    })
+                    .collect()
+            }),
+        })
+    }
+}
+
+/// Reads the options of every strategy, keyed by strategy name or `default`, from a JSON file.
+pub fn read_bundle_options(path: &Path) -> Result<HashMap<String, BundleOptions>, Box<dyn Error>> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read bundle options {}: {}", path.display(), e))?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Checks the privacy and validity settings of a bundle against its body, as the relay would.
+pub fn validate_bundle(bundle: &SendBundleRequest) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(validity) = &bundle.validity {
+        let refund = validity.refund.as_deref().unwrap_or_default();
+        for r in refund {
+            match bundle.bundle_body.get(r.body_idx as usize) {
+                None => {
+                    return Err(format!("Refund of body index {} out of bounds", r.body_idx).into())
+                }
+                // the refund goes to the sender of that tx, never ourselves
+                Some(BundleItem::Tx { .. }) => {
+                    return Err(
+                        format!("Refund of body index {} is one of our txs", r.body_idx).into(),
+                    )
+                }
+                Some(_) => {}
+            }
+        }
+        if refund.iter().map(|r| r.percent).sum::<u64>() > 100 {
+            return Err("Refund percents add up to more than 100".into());
+        }
+
+        let config = validity.refund_config.as_deref().unwrap_or_default();
+        if config.iter().any(|r| r.percent == 0) {
+            return Err("Refund config with a percent of 0".into());
+        }
+        if config.iter().map(|r| r.percent).sum::<u64>() > 100 {
+            return Err("Refund config percents add up to more than 100".into());
+        }
+    }
+
+    if let Some(builders) = bundle.privacy.as_ref().and_then(|p| p.builders.as_ref()) {
+        if builders.is_empty() {
+            return Err("Empty builder list, the bundle would never be built".into());
+        }
+        if builders.iter().any(|b| b.trim().is_empty()) {
+            return Err("Empty builder name".into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::types::{Bytes, H256};
+    use serde_json::json;
+
+    use super::*;
+
+    fn bundle(validity: Option<Validity>, builders: Option<Vec<&str>>) -> SendBundleRequest {
+        SendBundleRequest {
+            bundle_body: vec![
+                BundleItem::Hash {
+                    hash: H256::from_low_u64_be(1),
+                },
+                BundleItem::Tx {
+                    tx: Bytes::from(vec![1]),
+                    can_revert: false,
+                },
+            ],
+            validity,
+            privacy: builders.map(|builders| Privacy {
+                hints: None,
+                builders: Some(builders.into_iter().map(String::from).collect()),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn refund(refund: &[(u64, u64)]) -> Option<Validity> {
+        Some(Validity {
+            refund: Some(
+                refund
+                    .iter()
+                    .map(|(body_idx, percent)| Refund {
+                        body_idx: *body_idx,
+                        percent: *percent,
+                    })
+                    .collect(),
+            ),
+            refund_config: None,
+        })
+    }
+
+    fn refund_config(percents: &[u64]) -> Option<Validity> {
+        Some(Validity {
+            refund: None,
+            refund_config: Some(
+                percents
+                    .iter()
+                    .map(|percent| RefundConfig {
+                        address: H160::from_low_u64_be(1),
+                        percent: *percent,
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    #[test]
+    fn accepts_refunds_of_backrun_txs() {
+        assert!(validate_bundle(&bundle(None, None)).is_ok());
+        assert!(validate_bundle(&bundle(refund(&[(0, 90)]), Some(vec!["flashbots"]))).is_ok());
+        assert!(validate_bundle(&bundle(refund_config(&[60, 40]), None)).is_ok());
+    }
+
+    #[test]
+    fn rejects_refunds_out_of_bounds_or_to_our_txs() {
+        assert!(validate_bundle(&bundle(refund(&[(2, 50)]), None)).is_err());
+        assert!(validate_bundle(&bundle(refund(&[(1, 50)]), None)).is_err());
+    }
+
+    #[test]
+    fn rejects_percents_over_100() {
+        assert!(validate_bundle(&bundle(refund(&[(0, 60), (0, 50)]), None)).is_err());
+        assert!(validate_bundle(&bundle(refund_config(&[60, 50]), None)).is_err());
+        assert!(validate_bundle(&bundle(refund_config(&[100, 0]), None)).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_builders() {
+        assert!(validate_bundle(&bundle(None, Some(vec![]))).is_err());
+        assert!(validate_bundle(&bundle(None, Some(vec!["flashbots", " "]))).is_err());
+    }
+
+    #[test]
+    fn maps_options_to_the_rpc_types() {
+        let options = serde_json::from_value::<BundleOptions>(json!({
+            "hints": ["calldata", "tx_hash"],
+            "builders": ["flashbots"],
+            "refund": [{ "body_idx": 0, "percent": 90 }],
+            "refund_config": [{ "address": H160::from_low_u64_be(1), "percent": 100 }],
+        }))
+        .unwrap();
+
+        let privacy = serde_json::to_value(options.privacy().unwrap()).unwrap();
+        let hints = privacy["hints"].as_array().unwrap();
+        assert!(hints.contains(&json!("calldata")));
+        assert!(hints.contains(&json!("tx_hash")));
+        assert!(!hints.contains(&json!("logs")));
+        assert_eq!(privacy["builders"], json!(["flashbots"]));
+
+        let validity = options.validity().unwrap();
+        let refund = validity.refund.as_deref().unwrap();
+        assert_eq!((refund[0].body_idx, refund[0].percent), (0, 90));
+        let config = validity.refund_config.as_deref().unwrap();
+        assert_eq!(
+            (config[0].address, config[0].percent),
+            (H160::from_low_u64_be(1), 100)
+        );
+
+        let defaults = BundleOptions::default();
+        assert!(defaults.privacy().is_none());
+        assert!(defaults.validity().is_none());
+    }
+}
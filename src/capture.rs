@@ -0,0 +1,144 @@
+use std::{error::Error, sync::Arc};
+
+use ethers_core::{
+    abi::RawLog,
+    types::{Log, Transaction, TransactionReceipt, H160, U64},
+};
+use ethers_providers::Middleware;
+use futures_util::future::join_all;
+use tracing::{info, warn};
+
+use crate::{constants::LOGGER_CONTRACT, context::AppContext, contracts::LOGGER_ABI};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture {
+    pub challenge: H160,
+    pub capturer: H160,
+}
+
+/// Decodes a `FlagCaptured` event emitted by the logger contract.
+pub fn decode_capture(log: &Log) -> Result<Capture, Box<dyn Error + Send + Sync>> {
+    let mut challenge = None;
+    let mut capturer = None;
+    for param in LOGGER_ABI
+        .event("FlagCaptured")?
+        .parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })?
+        .params
+    {
+        match param.name.as_str() {
+            "challenge" => challenge = param.value.into_address(),
+            "receiver" => capturer = param.value.into_address(),
+            _ => {}
+        }
+    }
+    Ok(Capture {
+        challenge: challenge.ok_or("Missing challenge in logger event")?,
+        capturer: capturer.ok_or("Missing receiver in logger event")?,
+    })
+}
+
+/// Returns the challenges captured by `capturer` in this receipt.
+pub fn decode_captures(receipt: &TransactionReceipt, capturer: H160) -> Vec<H160> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == *LOGGER_CONTRACT)
+        .filter_map(|log| decode_capture(log).ok())
+        .filter(|capture| capture.capturer == capturer)
+        .map(|capture| capture.challenge)
+        .collect()
+}
+
+/// Fetches the receipts of our txs included in a block, records the gas paid and marks the
+/// challenges they captured. Reverted txs leave their target eligible for retry.
+pub async fn check_landed_txs(ctx: Arc<AppContext>, txs: Vec<Transaction>) {
+    let receipts = join_all(
+        txs.iter()
+            .map(|tx| ctx.rpc.get_transaction_receipt(tx.hash)),
+    )
+    .await;
+
+    for (tx, receipt) in txs.into_iter().zip(receipts) {
+        let receipt = match receipt {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => {
+                warn!(tx = ?tx.hash, "Missing receipt");
+                continue;
+            }
+            Err(e) => {
+                warn!(tx = ?tx.hash, "Error fetching receipt: {:?}", e);
+                continue;
+            }
+        };
+
+        if let Some(to) = tx.to {
+            let paid = receipt.gas_used.unwrap_or_default()
+                * receipt.effective_gas_price.unwrap_or_default();
+            ctx.progress.add_gas_paid(to, paid).await;
+        }
+
+        if receipt.status == Some(U64::zero()) {
+            warn!(tx = ?tx.hash, contract = ?tx.to, "Tx reverted, leaving it for retry");
+            continue;
+        }
+
+        for challenge in decode_captures(&receipt, ctx.address()) {
+            info!(contract = ?challenge, tx = ?tx.hash, "Captured challenge");
+            ctx.progress.add_progress_for_address(challenge).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers_core::{
+        types::{Bytes, Log, TransactionReceipt, H160, H256},
+        utils::keccak256,
+    };
+
+    use super::*;
+    use crate::constants::SIMPLE_CONTRACT_1;
+
+    /// A `FlagCaptured` receipt log laid out as the logger emits it: `receiver`, then
+    /// `challenge`, both indexed and no data.
+    fn capture_log(receiver: H160, challenge: H160) -> Log {
+        Log {
+            address: *LOGGER_CONTRACT,
+            topics: vec![
+                H256(keccak256("FlagCaptured(address,address)")),
+                H256::from(receiver),
+                H256::from(challenge),
+            ],
+            data: Bytes::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_receiver_then_challenge() {
+        let wallet = H160::from_str("0x00000000000000000000000000000000000c0ffe").unwrap();
+        let capture = decode_capture(&capture_log(wallet, *SIMPLE_CONTRACT_1)).unwrap();
+        assert_eq!(capture.capturer, wallet);
+        assert_eq!(capture.challenge, *SIMPLE_CONTRACT_1);
+    }
+
+    #[test]
+    fn decodes_only_our_captures() {
+        let wallet = H160::from_low_u64_be(1);
+        let other = H160::from_low_u64_be(2);
+        let receipt = TransactionReceipt {
+            logs: vec![
+                capture_log(wallet, *SIMPLE_CONTRACT_1),
+                capture_log(other, *SIMPLE_CONTRACT_1),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(decode_captures(&receipt, wallet), vec![*SIMPLE_CONTRACT_1]);
+        assert!(decode_captures(&receipt, H160::from_low_u64_be(3)).is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use ethers_core::types::H160;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Build and sign bundles but never send them to the relay
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    /// Append dry run bundles to this file as newline delimited JSON instead of printing them
+    #[arg(long, global = true)]
+    pub dry_run_out: Option<PathBuf>,
+    /// Record SSE events and blocks to this capture file as newline delimited JSON
+    #[arg(long, global = true)]
+    pub record: Option<PathBuf>,
+    /// Log output format, filtered with `RUST_LOG`
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Human)]
+    pub log_format: LogFormat,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Listen for SSE events and blocks and send bundles for matching strategies (default)
+    Run,
+    /// Print the progress of every wallet in `ctf_progress.json` against the known contracts
+    Status,
+    /// Clear the progress of a contract
+    Reset {
+        #[arg(long)]
+        contract: H160,
+        /// Only clear the progress of this wallet, all wallets otherwise
+        #[arg(long)]
+        wallet: Option<H160>,
+    },
+    /// Feed a capture file through the executor and progress logic as a dry run, saving nothing
+    Replay {
+        file: PathBuf,
+        /// Replay speed relative to the original pace, 0 to replay as fast as possible
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+    /// Score every strategy against a capture file with a mocked relay
+    Backtest { file: PathBuf },
+    /// Show which strategies match an event and the bundles they would build, as a dry run
+    Simulate {
+        /// The event as JSON, or a path to a file containing it
+        event: String,
+    },
+}
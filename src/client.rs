@@ -1,11 +1,48 @@
-use mev_share_rpc_api::MevApiClient;
+use std::{error::Error, sync::Arc, time::Duration};
 
+use ethers_core::rand::thread_rng;
+use ethers_signers::LocalWallet;
+use jsonrpsee::http_client::{transport, HttpClientBuilder};
+use mev_share_rpc_api::{FlashbotsSignerLayer, SendBundleRequest, SendBundleResponse};
+use tower::{retry::Retry, timeout::Timeout, ServiceBuilder, ServiceExt};
+
+use crate::relay::{RelayLimits, RetryPolicy, SendBundleService, TokenBucket, TokenBucketLayer};
+
+/// A relay client sending bundles through a retry, rate limit and timeout layer stack.
+#[derive(Clone)]
 pub struct Client {
-    pub inner: Box<dyn MevApiClient + Sync + Send>,
+    pub url: String,
+    pub service: Retry<RetryPolicy, TokenBucket<Timeout<SendBundleService>>>,
 }
 
-impl AsRef<dyn MevApiClient + Sync + Send> for Client {
-    fn as_ref(&self) -> &(dyn MevApiClient + Send + Sync + 'static) {
-        self.inner.as_ref()
+impl Client {
+    /// Connects to a relay, signing requests with a throwaway Flashbots identity.
+    pub fn new(url: &str, limits: &RelayLimits) -> Result<Self, Box<dyn Error>> {
+        let fb_signer = LocalWallet::new(&mut thread_rng());
+        let signing_middleware = FlashbotsSignerLayer::new(fb_signer);
+        let service_builder = ServiceBuilder::new()
+            .map_err(transport::Error::Http)
+            .layer(signing_middleware);
+        let rpc_client = HttpClientBuilder::default()
+            .set_middleware(service_builder)
+            .build(url)?;
+        let service = ServiceBuilder::new()
+            .retry(RetryPolicy::new(limits))
+            .layer(TokenBucketLayer::new(limits.rate, limits.burst))
+            .timeout(Duration::from_millis(limits.timeout_ms))
+            .service(SendBundleService {
+                client: Arc::new(rpc_client),
+            });
+        Ok(Client {
+            url: url.to_string(),
+            service,
+        })
+    }
+
+    pub async fn send_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>> {
+        self.service.clone().oneshot(bundle).await
     }
 }
@@ -0,0 +1,325 @@
+use std::{collections::BTreeSet, error::Error, path::Path, sync::Arc};
+
+use ethers_core::{
+    types::{Block, Filter, H160, U256},
+    utils::format_ether,
+};
+use ethers_providers::{Middleware, Provider};
+use ethers_signers::Signer;
+use futures_util::{future::join_all, StreamExt};
+use mev_share_sse::Event;
+use tracing::{error, info, warn};
+
+use crate::{
+    admin::serve_admin,
+    backtest::{finish_backtest, report_match, start_backtest},
+    budget::Budget,
+    bundle_options::DEFAULT_OPTIONS,
+    capture::check_landed_txs,
+    config::Config,
+    constants::{CONTRACTS, LOGGER_CONTRACT},
+    context::AppContext,
+    executor::Executor,
+    metrics::serve_metrics,
+    progress::{read_ctf_progress, write_ctf_progress, Progress},
+    reconcile::reconcile,
+    recorder::{Record, ReplaySource},
+    shutdown::{drain, shutdown_signal},
+    sink::{MockBehaviour, MockRelay},
+    source::EventSource,
+};
+
+/// Runs until shutdown, or until the block, event or logger stream ends, which is returned as an
+/// error once in-flight handlers are drained.
+pub async fn run(ctx: Arc<AppContext>, source: &dyn EventSource) -> Result<(), Box<dyn Error>> {
+    info!(wallet = ?ctx.address(), "Interacting with wallet");
+    info!(init_codes = ?ctx.init_code_hashes, "Loaded init code hashes");
+
+    let strategies = Executor::strategy_names();
+    for name in ctx.config.bundle_options.keys() {
+        if name != DEFAULT_OPTIONS && !strategies.contains(&name.as_str()) {
+            warn!(strategy = %name, "Bundle options for an unknown strategy");
+        }
+    }
+
+    if let Some(from) = ctx.config.reconcile_from_block {
+        reconcile(&ctx, from, ctx.config.reconcile_to_block)
+            .await
+            .map_err(|e| format!("Could not reconcile progress: {}", e))?;
+    }
+
+    ctx.rpc
+        .as_ref()
+        .spawn_health_checks(ctx.config.rpc_health_interval);
+    if let Some(addr) = ctx.config.admin_addr {
+        serve_admin(ctx.clone(), addr)
+            .map_err(|e| format!("Could not start admin server: {}", e))?;
+    }
+    if let Some(addr) = ctx.config.metrics_addr {
+        serve_metrics(ctx.metrics.clone(), addr)
+            .map_err(|e| format!("Could not start metrics server: {}", e))?;
+    }
+
+    let client = Provider::connect(&ctx.config.ws_url)
+        .await
+        .map_err(|e| format!("Could not connect to WS endpoint: {}", e))?;
+
+    let f1 = async {
+        let mut stream = client.subscribe_blocks().await?;
+        while let Some(Block {
+            hash: Some(hash),
+            number: Some(number),
+            timestamp,
+            ..
+        }) = stream.next().await
+        {
+            info!(block = %number, ?hash, %timestamp, "Got block");
+            ctx.progress.set_latest_block(number).await;
+            ctx.metrics.observe_block(timestamp);
+            ctx.metrics.observe_progress(&ctx.progress).await;
+            let completed = ctx
+                .progress
+                .inner
+                .read()
+                .await
+                .get(&ctx.address())
+                .cloned()
+                .unwrap_or_default();
+            ctx.admin.prune(number, &completed).await;
+
+            // the budget keeps checking against the last known balance until this succeeds again
+            match ctx.rpc.get_balance(ctx.address(), Some(hash.into())).await {
+                Ok(balance) => {
+                    ctx.budget.set_balance(balance);
+                    ctx.metrics.observe_balance(balance);
+                }
+                Err(e) => warn!(block = %number, "Could not fetch wallet balance: {:?}", e),
+            }
+
+            if let Some(block) = ctx.rpc.get_block_with_txs(hash).await? {
+                if let Some(recorder) = &ctx.recorder {
+                    recorder.record_block(&block);
+                }
+                ctx.discovery.discover_from_block(&block).await;
+                ctx.pending.on_block(&block);
+                let txs = block
+                    .transactions
+                    .into_iter()
+                    .filter(|tx| tx.from == ctx.address())
+                    .collect::<Vec<_>>();
+                if !txs.is_empty() {
+                    ctx.tasks.spawn(check_landed_txs(ctx.clone(), txs));
+                }
+            }
+        }
+        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+    };
+    let f2 = async {
+        let mut stream = source.events().await?;
+        while let Some(event) = stream.next().await {
+            if let Some(recorder) = &ctx.recorder {
+                recorder.record_event(&event);
+            }
+            Executor::dispatch(ctx.clone(), event);
+        }
+
+        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+    };
+    let f3 = async {
+        let filter = Filter::new().address(*LOGGER_CONTRACT);
+        let mut stream = client.subscribe_logs(&filter).await?;
+        while let Some(log) = stream.next().await {
+            if let Err(e) = ctx.discovery.discover_from_logger_log(&log).await {
+                error!(tx = ?log.transaction_hash, "Error decoding logger event: {:?}", e);
+            }
+        }
+        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+    };
+    // the others are useless without any one of the streams, stop as soon as one ends
+    let streams = tokio::select! {
+        res = f1 => Err(stream_ended("Block subscription", res)),
+        res = f2 => Err(stream_ended("Event stream", res)),
+        res = f3 => Err(stream_ended("Logger subscription", res)),
+        _ = shutdown_signal() => {
+            info!("Shutting down, no longer taking new events or blocks");
+            Ok(())
+        }
+    };
+
+    // jobs already queued still get to send their bundles when a stream failed
+    drain(&ctx, ctx.config.shutdown_timeout).await;
+    Ok(streams?)
+}
+
+fn stream_ended(stream: &str, res: Result<(), Box<dyn Error + Send + Sync>>) -> String {
+    match res {
+        Ok(()) => format!("{} ended", stream),
+        Err(e) => format!("{} failed: {}", stream, e),
+    }
+}
+
+pub fn status(path: &Path) -> Result<(), Box<dyn Error>> {
+    let progress = read_ctf_progress(path)?;
+    let wallets = progress
+        .completed
+        .keys()
+        .chain(progress.gas_paid.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    if wallets.is_empty() {
+        println!("No progress recorded yet");
+    }
+
+    let mut contracts = CONTRACTS.iter().collect::<Vec<_>>();
+    contracts.sort();
+    for wallet in wallets {
+        let completed = progress.completed.get(&wallet).cloned().unwrap_or_default();
+        let gas_paid = progress.gas_paid.get(&wallet).cloned().unwrap_or_default();
+        let gas = |contract: &H160| match gas_paid.get(contract) {
+            Some(paid) => format!(" ({} ETH gas)", format_ether(*paid)),
+            None => String::new(),
+        };
+        println!(
+            "Wallet {:?}: {}/{} contracts completed, {} ETH gas paid",
+            wallet,
+            contracts.iter().filter(|c| completed.contains(**c)).count(),
+            contracts.len(),
+            format_ether(
+                gas_paid
+                    .values()
+                    .fold(U256::zero(), |total, paid| total + *paid)
+            )
+        );
+        for contract in &contracts {
+            let mark = if completed.contains(*contract) {
+                "x"
+            } else {
+                " "
+            };
+            println!("  [{}] {:?}{}", mark, contract, gas(contract));
+        }
+        for contract in completed.iter().filter(|c| !CONTRACTS.contains(*c)) {
+            println!("  [x] {:?} (unknown contract){}", contract, gas(contract));
+        }
+    }
+    Ok(())
+}
+
+pub fn reset(path: &Path, contract: H160, wallet: Option<H160>) -> Result<(), Box<dyn Error>> {
+    let mut progress = read_ctf_progress(path)?;
+    for (address, completed) in progress.completed.iter_mut() {
+        if wallet.map(|w| w == *address).unwrap_or(true) && completed.remove(&contract) {
+            println!("Cleared {:?} for wallet {:?}", contract, address);
+        }
+    }
+    write_ctf_progress(path, &progress)?;
+    Ok(())
+}
+
+pub async fn replay(ctx: Arc<AppContext>, file: &Path, speed: f64) -> Result<(), Box<dyn Error>> {
+    ctx.progress
+        .set_latest_block(ctx.rpc.get_block_number().await?)
+        .await;
+
+    let mut source = ReplaySource::open(file, speed)?;
+    let mut handles = vec![];
+    while let Some(entry) = source.next().await {
+        match entry?.record {
+            Record::Event(event) => {
+                let (matched, handlers): (Vec<_>, Vec<_>) =
+                    Executor::match_event(&ctx, &event).into_iter().unzip();
+                info!(hash = ?event.hash, ?matched, "Replaying event");
+                handles.extend(handlers.into_iter().map(tokio::spawn));
+            }
+            Record::Block(block) => {
+                if let Some(number) = block.number {
+                    info!(block = %number, "Replaying block");
+                    ctx.progress.set_latest_block(number).await;
+                }
+                ctx.pending.on_block(&block);
+                let txs = block
+                    .transactions
+                    .into_iter()
+                    .filter(|tx| tx.from == ctx.address())
+                    .collect::<Vec<_>>();
+                if !txs.is_empty() {
+                    handles.push(tokio::spawn(check_landed_txs(ctx.clone(), txs)));
+                }
+            }
+        }
+    }
+    join_all(handles).await;
+    Ok(())
+}
+
+pub async fn simulate(ctx: Arc<AppContext>, event: &str) -> Result<(), Box<dyn Error>> {
+    let data = if Path::new(event).is_file() {
+        std::fs::read_to_string(event)?
+    } else {
+        event.to_string()
+    };
+    let event = serde_json::from_str::<Event>(&data)?;
+
+    ctx.progress
+        .set_latest_block(ctx.rpc.get_block_number().await?)
+        .await;
+
+    let (matched, handlers): (Vec<_>, Vec<_>) =
+        Executor::match_event(&ctx, &event).into_iter().unzip();
+    if matched.is_empty() {
+        info!(hash = ?event.hash, "No strategy matched event");
+        return Ok(());
+    }
+    info!(?matched, "Matched strategies");
+    join_all(handlers).await;
+    Ok(())
+}
+
+pub async fn backtest(config: Config, file: &Path) -> Result<(), Box<dyn Error>> {
+    let wallet = config.wallet.address();
+    // score every strategy offline, nothing is saved to disk or spent during a backtest
+    let ctx = Arc::new(
+        AppContext::new(config, Arc::new(MockRelay::new(MockBehaviour::Accept)))?
+            .with_progress(Progress::new(wallet))
+            .with_budget(Budget::unlimited())
+            .with_fixed_nonce(U256::zero()),
+    );
+    start_backtest(&ctx);
+
+    let mut source = ReplaySource::open(file, 0.0)?;
+    let mut blocks = vec![];
+    while let Some(entry) = source.next().await {
+        match entry?.record {
+            Record::Event(event) => {
+                let handlers = Executor::match_event(&ctx, &event)
+                    .into_iter()
+                    .map(|(name, handler)| {
+                        report_match(&ctx, name);
+                        handler
+                    })
+                    .collect::<Vec<_>>();
+                join_all(handlers).await;
+            }
+            Record::Block(block) => {
+                if let Some(number) = block.number {
+                    ctx.progress.set_latest_block(number).await;
+                }
+                ctx.pending.on_block(&block);
+                blocks.push(block);
+            }
+        }
+    }
+
+    let stats = finish_backtest(&ctx, &Executor::strategy_names(), &blocks);
+    println!(
+        "{:<24} {:>8} {:>8} {:>9} {:>8} {:>9} {:>8}",
+        "strategy", "matched", "built", "undecoded", "failed", "landable", "landed"
+    );
+    for (strategy, s) in stats {
+        println!(
+            "{:<24} {:>8} {:>8} {:>9} {:>8} {:>9} {:>8}",
+            strategy, s.matched, s.built, s.decode_failed, s.failed, s.landable, s.landed
+        );
+    }
+    Ok(())
+}
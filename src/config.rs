@@ -0,0 +1,276 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use ethers_core::{
+    types::{H160, U256, U64},
+    utils::parse_ether,
+};
+use ethers_signers::{LocalWallet, Signer};
+
+use crate::{
+    bundle_options::{read_bundle_options, BundleOptions, DEFAULT_OPTIONS},
+    constants::{RELAY, SSE},
+    relay::{read_relay_limits, RelayLimits},
+};
+
+/// Limits of the handler work queue.
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Jobs running at once.
+    pub workers: usize,
+    /// Jobs waiting or running before new ones are dropped.
+    pub queue_size: usize,
+    /// Jobs of one strategy running at once, unless overridden in `strategy_limits`.
+    pub strategy_limit: usize,
+    pub strategy_limits: HashMap<String, usize>,
+    /// Candidates a single handler (e.g. magic numbers) tries at once.
+    pub candidate_concurrency: usize,
+}
+
+/// Wallet spend limits, in wei.
+#[derive(Debug, Clone)]
+pub struct BudgetConfig {
+    /// No bundle is built while the wallet balance is below this.
+    pub floor: U256,
+    pub max_per_bundle: U256,
+    pub max_per_hour: U256,
+}
+
+/// Everything an `AppContext` is built from, read from the environment (and `.env`).
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// HTTP RPC endpoints, failed over between by `FailoverClient`.
+    pub rpc_urls: Vec<String>,
+    /// How often every RPC endpoint is health checked.
+    pub rpc_health_interval: Duration,
+    pub ws_url: String,
+    pub sse_url: String,
+    pub relay_url: String,
+    /// Rate limit, timeout and retries for `relay_url`.
+    pub relay_limits: RelayLimits,
+    pub wallet: LocalWallet,
+    pub progress_path: PathBuf,
+    pub init_codes_path: PathBuf,
+    /// Accounts whose contract creation txs are proposed as discovery candidates.
+    pub deployers: HashSet<H160>,
+    pub auto_register: bool,
+    /// Keep the events seen as `PendingHints`, for `backrun_pending`.
+    pub pending_hints: bool,
+    pub reconcile_from_block: Option<U64>,
+    pub reconcile_to_block: Option<U64>,
+    pub reconcile_chunk: u64,
+    /// Address to serve Prometheus metrics on, disabled if unset.
+    pub metrics_addr: Option<SocketAddr>,
+    /// Address to serve the admin API on, disabled if unset.
+    pub admin_addr: Option<SocketAddr>,
+    /// How long to wait for in-flight handlers on shutdown.
+    pub shutdown_timeout: Duration,
+    pub queue: QueueConfig,
+    pub budget: BudgetConfig,
+    /// Privacy and validity settings by strategy name, or `default`.
+    pub bundle_options: HashMap<String, BundleOptions>,
+}
+
+/// Reads a variable from the environment or `.env`, treating empty values as unset.
+pub fn var(key: &str) -> Option<String> {
+    dotenv::vars()
+        .find(|e| e.0 == key)
+        .map(|e| e.1)
+        .filter(|v| !v.is_empty())
+}
+
+/// Reads the file named by `key`, failing if it is missing, or else `default` if it exists.
+fn optional_file<T: Default>(
+    key: &str,
+    default: &str,
+    read: fn(&Path) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    match var(key) {
+        Some(path) => read(path.as_ref()),
+        None if Path::new(default).exists() => read(default.as_ref()),
+        None => Ok(T::default()),
+    }
+}
+
+pub fn progress_path() -> PathBuf {
+    var("PROGRESS_FILE")
+        .unwrap_or_else(|| "ctf_progress.json".to_string())
+        .into()
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let chain_id = match var("CHAIN_ID") {
+            Some(chain_id) => chain_id.parse::<u64>()?,
+            None => 5,
+        };
+        if var("FACTORIES").is_some() {
+            return Err(
+                "FACTORIES was renamed to DEPLOYERS, only their own creation txs are seen".into(),
+            );
+        }
+        let deployers = var("DEPLOYERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|a| !a.trim().is_empty())
+            .map(|a| H160::from_str(a.trim()))
+            .collect::<Result<_, _>>()?;
+        // a limit of 0 would park or drop every job
+        let number = |key: &str, default: usize| -> Result<usize, Box<dyn Error>> {
+            match var(key) {
+                Some(number) => match number.parse()? {
+                    0 => Err(format!("{} must be positive", key).into()),
+                    number => Ok(number),
+                },
+                None => Ok(default),
+            }
+        };
+        let strategy_limits = var("STRATEGY_LIMITS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| -> Result<(String, usize), Box<dyn Error>> {
+                let (name, limit) = l
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid strategy limit {}", l))?;
+                match limit.trim().parse()? {
+                    0 => Err(format!("Strategy limit of {} must be positive", name.trim()).into()),
+                    limit => Ok((name.trim().to_string(), limit)),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let relay_url = var("RELAY").unwrap_or_else(|| RELAY.to_string());
+        let relay_limits = optional_file("RELAYS", "relays.json", read_relay_limits)?;
+        let ether = |key: &str, default: &str| -> Result<U256, Box<dyn Error>> {
+            Ok(parse_ether(
+                var(key).unwrap_or_else(|| default.to_string()),
+            )?)
+        };
+        let block = |key: &str| -> Result<Option<U64>, Box<dyn Error>> {
+            Ok(match var(key) {
+                Some(block) => Some(U64::from(block.parse::<u64>()?)),
+                None => None,
+            })
+        };
+
+        Ok(Self {
+            rpc_urls: var("RPC")
+                .ok_or("Cannot find RPC URL in ENV")?
+                .split(',')
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .collect(),
+            rpc_health_interval: Duration::from_secs(match var("RPC_HEALTH_INTERVAL") {
+                Some(interval) => interval.parse()?,
+                None => 5,
+            }),
+            ws_url: var("WS").ok_or("Cannot find WS URL in ENV")?,
+            sse_url: var("SSE").unwrap_or_else(|| SSE.to_string()),
+            relay_limits: relay_limits.get(&relay_url).cloned().unwrap_or_default(),
+            relay_url,
+            wallet: var("WALLET")
+                .ok_or("Cannot find wallet private key in ENV")?
+                .parse::<LocalWallet>()?
+                .with_chain_id(chain_id),
+            progress_path: progress_path(),
+            init_codes_path: var("INIT_CODES")
+                .unwrap_or_else(|| "init_codes.json".to_string())
+                .into(),
+            deployers,
+            auto_register: var("AUTO_REGISTER")
+                .map(|e| e == "true")
+                .unwrap_or_default(),
+            pending_hints: var("PENDING_HINTS")
+                .map(|e| e == "true")
+                .unwrap_or_default(),
+            reconcile_from_block: block("RECONCILE_FROM_BLOCK")?,
+            reconcile_to_block: block("RECONCILE_TO_BLOCK")?,
+            reconcile_chunk: match var("RECONCILE_CHUNK") {
+                Some(chunk) => match chunk.parse()? {
+                    0 => return Err("RECONCILE_CHUNK must be positive".into()),
+                    chunk => chunk,
+                },
+                None => 10000,
+            },
+            metrics_addr: var("METRICS_ADDR").map(|a| a.parse()).transpose()?,
+            admin_addr: var("ADMIN_ADDR").map(|a| a.parse()).transpose()?,
+            shutdown_timeout: Duration::from_secs(match var("SHUTDOWN_TIMEOUT") {
+                Some(timeout) => timeout.parse()?,
+                None => 30,
+            }),
+            queue: QueueConfig {
+                workers: number("WORKERS", 32)?,
+                queue_size: number("QUEUE_SIZE", 1024)?,
+                strategy_limit: number("STRATEGY_LIMIT", 8)?,
+                strategy_limits,
+                candidate_concurrency: number("CANDIDATE_CONCURRENCY", 8)?,
+            },
+            bundle_options: optional_file(
+                "BUNDLE_OPTIONS",
+                "bundle_options.json",
+                read_bundle_options,
+            )?,
+            budget: BudgetConfig {
+                floor: ether("BALANCE_FLOOR", "0.1")?,
+                max_per_bundle: ether("MAX_BUNDLE_SPEND", "0.2")?,
+                max_per_hour: ether("MAX_HOURLY_SPEND", "2")?,
+            },
+        })
+    }
+
+    /// A config for tests, with a throwaway wallet and endpoints that are never connected to.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn for_tests(progress_path: PathBuf) -> Self {
+        Self {
+            rpc_urls: vec!["http://127.0.0.1:8545".to_string()],
+            rpc_health_interval: Duration::from_secs(5),
+            ws_url: "ws://127.0.0.1:8546".to_string(),
+            sse_url: SSE.to_string(),
+            relay_url: RELAY.to_string(),
+            relay_limits: RelayLimits::default(),
+            wallet: "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse::<LocalWallet>()
+                .unwrap()
+                .with_chain_id(5u64),
+            progress_path,
+            init_codes_path: "init_codes.json".into(),
+            deployers: HashSet::new(),
+            auto_register: false,
+            pending_hints: false,
+            reconcile_from_block: None,
+            reconcile_to_block: None,
+            reconcile_chunk: 10000,
+            metrics_addr: None,
+            admin_addr: None,
+            shutdown_timeout: Duration::from_secs(5),
+            queue: QueueConfig {
+                workers: 4,
+                queue_size: 64,
+                strategy_limit: 4,
+                strategy_limits: HashMap::new(),
+                candidate_concurrency: 4,
+            },
+            budget: BudgetConfig {
+                floor: U256::zero(),
+                max_per_bundle: U256::MAX,
+                max_per_hour: U256::MAX,
+            },
+            bundle_options: HashMap::new(),
+        }
+    }
+
+    /// The options of a strategy, falling back to the `default` ones.
+    pub fn bundle_options(&self, strategy: Option<&str>) -> BundleOptions {
+        strategy
+            .and_then(|s| self.bundle_options.get(s))
+            .or_else(|| self.bundle_options.get(DEFAULT_OPTIONS))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+use ethers_core::types::{H160, H256, U256};
+use ethers_providers::Provider;
+use ethers_signers::{LocalWallet, Signer};
+use mev_share_sse::EventClient;
+use tokio_util::task::TaskTracker;
+
+use crate::{
+    admin::AdminState, backtest::Backtest, budget::Budget, config::Config,
+    constants::NEW_CONTRACT_INIT_CODE, discovery::Discovery, init_code::read_init_code_hashes,
+    metrics::Metrics, pending::PendingHints, progress::Progress, queue::WorkQueue,
+    recorder::Recorder, rpc::FailoverClient, sink::BundleSink,
+};
+
+/// Everything the executor and handlers need, so several isolated instances can run in one
+/// process.
+pub struct AppContext {
+    pub config: Config,
+    pub rpc: Provider<FailoverClient>,
+    pub event_client: EventClient,
+    pub sink: Arc<dyn BundleSink>,
+    pub progress: Progress,
+    pub discovery: Discovery,
+    pub init_code_hashes: HashMap<String, H256>,
+    pub recorder: Option<Recorder>,
+    pub backtest: Mutex<Option<Backtest>>,
+    pub metrics: Metrics,
+    pub admin: AdminState,
+    /// Every handler task, waited on at shutdown.
+    pub tasks: TaskTracker,
+    pub queue: WorkQueue,
+    pub budget: Budget,
+    pub pending: PendingHints,
+    /// Nonce used instead of asking the RPC, for backtests of recorded traffic.
+    pub fixed_nonce: Option<U256>,
+}
+
+impl AppContext {
+    pub fn new(config: Config, sink: Arc<dyn BundleSink>) -> Result<Self, Box<dyn Error>> {
+        let init_code_hashes = read_init_code_hashes(&config.init_codes_path)?;
+        if !init_code_hashes.contains_key(NEW_CONTRACT_INIT_CODE) {
+            return Err(format!(
+                "Missing {} init code in {}",
+                NEW_CONTRACT_INIT_CODE,
+                config.init_codes_path.display()
+            )
+            .into());
+        }
+        let metrics = Metrics::new()?;
+        let tasks = TaskTracker::new();
+        Ok(Self {
+            rpc: Provider::new(FailoverClient::new(&config.rpc_urls)?),
+            event_client: EventClient::default(),
+            sink,
+            progress: Progress::read(&config.progress_path, config.wallet.address())?,
+            discovery: Discovery::new(config.auto_register, config.deployers.clone()),
+            init_code_hashes,
+            recorder: None,
+            backtest: Mutex::new(None),
+            queue: WorkQueue::new(config.queue.clone(), metrics.clone(), tasks.clone()),
+            metrics,
+            admin: AdminState::default(),
+            budget: Budget::new(config.budget.clone()),
+            pending: PendingHints::default(),
+            fixed_nonce: None,
+            tasks,
+            config,
+        })
+    }
+
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn with_fixed_nonce(mut self, nonce: U256) -> Self {
+        self.fixed_nonce = Some(nonce);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn wallet(&self) -> &LocalWallet {
+        &self.config.wallet
+    }
+
+    pub fn address(&self) -> H160 {
+        self.config.wallet.address()
+    }
+
+    /// Looks up an init code hash computed at startup by its configured name.
+    pub fn init_code_hash(&self, name: &str) -> Result<H256, Box<dyn Error + Send + Sync>> {
+        self.init_code_hashes
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Unknown init code {}", name).into())
+    }
+}
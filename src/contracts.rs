@@ -7,6 +7,10 @@ lazy_static! {
         "function claimReward(uint256 _magicNumber)",
     ])
     .unwrap();
+    /// `MevShareCaptureLogger`, emitting the capturing wallet first, then the challenge.
+    pub static ref LOGGER_ABI: Abi =
+        parse_abi(&["event FlagCaptured(address indexed receiver, address indexed challenge)"])
+            .unwrap();
     pub static ref NEW_CONTRACT_ABI: Abi = parse_abi(&[
         "event Activate(address newlyDeployedContract)",
         "event ActivateBySalt(bytes32 salt)",
@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::{Arc, RwLock as SyncRwLock},
+};
+
+use ethers_core::{
+    types::{Block, Log, Transaction, H160, H256, U64},
+    utils::get_contract_address,
+};
+use mev_share_sse::EventTransactionLog;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::{
+    capture::decode_capture,
+    constants::{CONTRACTS, LOGGER_CONTRACT, NEW_CONTRACT_CONTRACT},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub enum CandidateSource {
+    Logger,
+    Deployer(H160),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub address: H160,
+    pub source: CandidateSource,
+    pub first_seen_block: Option<U64>,
+    pub event_signatures: HashSet<H256>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    pub candidates: Arc<RwLock<HashMap<H160, Candidate>>>,
+    pub registered: Arc<RwLock<HashSet<H160>>>,
+    /// Addresses of every candidate, checked without awaiting for each SSE log.
+    watched: Arc<SyncRwLock<HashSet<H160>>>,
+    pub auto_register: bool,
+    /// Accounts whose contract creation txs are proposed as candidates. Contracts a factory
+    /// contract creates inside a call are not seen.
+    pub deployers: HashSet<H160>,
+}
+
+impl Discovery {
+    pub fn new(auto_register: bool, deployers: HashSet<H160>) -> Self {
+        Self {
+            candidates: Arc::new(RwLock::new(HashMap::new())),
+            registered: Arc::new(RwLock::new(HashSet::new())),
+            watched: Arc::new(SyncRwLock::new(HashSet::new())),
+            auto_register,
+            deployers,
+        }
+    }
+
+    pub fn is_known(address: H160) -> bool {
+        CONTRACTS.contains(&address)
+            || address == *NEW_CONTRACT_CONTRACT
+            || address == *LOGGER_CONTRACT
+    }
+
+    /// Whether logs from `address` are of interest to discovery, i.e. it is a candidate.
+    pub fn is_candidate(&self, address: H160) -> bool {
+        self.watched.read().unwrap().contains(&address)
+    }
+
+    pub async fn propose(&self, address: H160, source: CandidateSource, block: Option<U64>) {
+        if Self::is_known(address) {
+            return;
+        }
+
+        let mut candidates = self.candidates.write().await;
+        if candidates.contains_key(&address) {
+            return;
+        }
+        info!(contract = ?address, ?source, "Discovered candidate");
+        candidates.insert(
+            address,
+            Candidate {
+                address,
+                source,
+                first_seen_block: block,
+                event_signatures: HashSet::new(),
+            },
+        );
+        self.watched.write().unwrap().insert(address);
+        drop(candidates);
+
+        if self.auto_register {
+            self.register(address).await;
+        }
+    }
+
+    /// Records the event signature of a log emitted by a candidate.
+    pub async fn observe_log(&self, log: &EventTransactionLog) {
+        let Some(topic) = log.topics.first() else {
+            return;
+        };
+        if let Some(candidate) = self.candidates.write().await.get_mut(&log.address) {
+            if candidate.event_signatures.insert(*topic) {
+                info!(
+                    contract = ?candidate.address,
+                    events = ?candidate.event_signatures,
+                    "Candidate emits new event"
+                );
+            }
+        }
+    }
+
+    pub async fn register(&self, address: H160) {
+        info!(contract = ?address, "Registering candidate as target");
+        self.registered.write().await.insert(address);
+    }
+
+    pub async fn is_registered(&self, address: H160) -> bool {
+        self.registered.read().await.contains(&address)
+    }
+
+    pub async fn get_candidates(&self) -> Vec<Candidate> {
+        self.candidates.read().await.values().cloned().collect()
+    }
+
+    /// Proposes the challenge referenced by a logger contract event.
+    pub async fn discover_from_logger_log(
+        &self,
+        log: &Log,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let challenge = decode_capture(log)?.challenge;
+        self.propose(challenge, CandidateSource::Logger, log.block_number)
+            .await;
+        Ok(())
+    }
+
+    /// Proposes contracts created in this block by a top-level creation tx of a deployer.
+    pub async fn discover_from_block(&self, block: &Block<Transaction>) {
+        for tx in &block.transactions {
+            if tx.to.is_none() && self.deployers.contains(&tx.from) {
+                let address = get_contract_address(tx.from, tx.nonce);
+                self.propose(address, CandidateSource::Deployer(tx.from), block.number)
+                    .await;
+            }
+        }
+    }
+}
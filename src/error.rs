@@ -1 +1,6 @@
+use thiserror::Error;
 
+/// A log hint a strategy matched but could not decode, counted apart from other handler errors.
+#[derive(Debug, Error)]
+#[error("Cannot decode {0}")]
+pub struct DecodeError(pub String);
@@ -1,15 +1,20 @@
-use std::str::FromStr;
+use std::{collections::HashMap, sync::Arc};
 
-use ethers_core::types::H256;
+use ethers_core::types::{H160, H256};
 use futures_util::future::BoxFuture;
+use lazy_static::lazy_static;
 use mev_share_sse::{Event, EventTransaction, EventTransactionLog};
+use tracing::{debug, info_span, Instrument};
 
 use crate::{
+    backtest::with_strategy,
     constants::{
-        MAGIC_CONTRACT_1, MAGIC_CONTRACT_2, MAGIC_CONTRACT_3, NEW_CONTRACT_CONTRACT,
-        SIMPLE_CONTRACT_1, SIMPLE_CONTRACT_2, SIMPLE_CONTRACT_3, SIMPLE_CONTRACT_4,
-        SIMPLE_CONTRACT_TRIPLE,
+        MAGIC_ACTIVATE_TOPIC, MAGIC_CONTRACT_1, MAGIC_CONTRACT_2, MAGIC_CONTRACT_3,
+        NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC, NEW_CONTRACT_ACTIVATE_TOPIC, NEW_CONTRACT_CONTRACT,
+        SIMPLE_ACTIVATE_TOPIC, SIMPLE_CONTRACT_1, SIMPLE_CONTRACT_2, SIMPLE_CONTRACT_3,
+        SIMPLE_CONTRACT_4, SIMPLE_CONTRACT_TRIPLE,
     },
+    context::AppContext,
     handler::{
         backrun_create_contract_addr, backrun_create_contract_salt, backrun_magic_numba,
         backrun_simple, backrun_simple_triple,
@@ -19,20 +24,106 @@ use crate::{
 pub struct Executor;
 
 pub type Predicate<T> = fn(&T) -> bool;
-pub type Handler<T> = fn(T) -> BoxFuture<'static, ()>;
-pub type HashHandler<T> = fn(H256, T) -> BoxFuture<'static, ()>;
+pub type Handler<T> = fn(Arc<AppContext>, T) -> BoxFuture<'static, ()>;
+pub type HashHandler<T> = fn(Arc<AppContext>, H256, T) -> BoxFuture<'static, ()>;
+pub type Strategy<T, H> = (&'static str, Option<IndexKey>, Predicate<T>, H);
+
+/// What a strategy's predicate requires, so it is only evaluated against matching txs and logs.
+/// Strategies without a key are evaluated against every tx or log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexKey {
+    /// A tx to this address.
+    To(H160),
+    /// A log emitted by this address with this `topic0`.
+    Log(H160, H256),
+}
+
+fn has_topic(log: &EventTransactionLog, topic: H256) -> bool {
+    log.topics.first() == Some(&topic)
+}
+
+lazy_static! {
+    /// Every strategy, indexed by the key its predicate requires.
+    pub static ref STRATEGIES: StrategyIndex = StrategyIndex::new();
+}
+
+#[derive(Default)]
+pub struct StrategyIndex {
+    events: Vec<Strategy<Event, Handler<Event>>>,
+    txs: HashMap<H160, Vec<Strategy<EventTransaction, HashHandler<EventTransaction>>>>,
+    unindexed_txs: Vec<Strategy<EventTransaction, HashHandler<EventTransaction>>>,
+    logs:
+        HashMap<(H160, H256), Vec<Strategy<EventTransactionLog, HashHandler<EventTransactionLog>>>>,
+    unindexed_logs: Vec<Strategy<EventTransactionLog, HashHandler<EventTransactionLog>>>,
+}
+
+impl StrategyIndex {
+    pub fn new() -> Self {
+        let mut index = Self {
+            events: Executor::handle_event(),
+            ..Default::default()
+        };
+        for strategy in Executor::handle_tx() {
+            match strategy.1 {
+                Some(IndexKey::To(to)) => index.txs.entry(to).or_default().push(strategy),
+                _ => index.unindexed_txs.push(strategy),
+            }
+        }
+        for strategy in Executor::handle_log() {
+            match strategy.1 {
+                Some(IndexKey::Log(address, topic)) => index
+                    .logs
+                    .entry((address, topic))
+                    .or_default()
+                    .push(strategy),
+                _ => index.unindexed_logs.push(strategy),
+            }
+        }
+        index
+    }
+
+    /// Strategies whose predicate may match this tx.
+    pub fn txs(
+        &self,
+        tx: &EventTransaction,
+    ) -> impl Iterator<Item = &Strategy<EventTransaction, HashHandler<EventTransaction>>> {
+        tx.to
+            .and_then(|to| self.txs.get(&to))
+            .into_iter()
+            .flatten()
+            .chain(&self.unindexed_txs)
+    }
+
+    /// Strategies whose predicate may match this log.
+    pub fn logs(
+        &self,
+        log: &EventTransactionLog,
+    ) -> impl Iterator<Item = &Strategy<EventTransactionLog, HashHandler<EventTransactionLog>>>
+    {
+        log.topics
+            .first()
+            .and_then(|topic| self.logs.get(&(log.address, *topic)))
+            .into_iter()
+            .flatten()
+            .chain(&self.unindexed_logs)
+    }
+}
 
 impl Executor {
-    pub fn handle_event() -> Vec<(Predicate<Event>, Handler<Event>)> {
+    pub fn handle_event() -> Vec<Strategy<Event, Handler<Event>>> {
         vec![(
+            "simple_contract_3",
+            None,
             |event| event.logs.is_empty() && event.transactions.is_empty(),
-            |event| Box::pin(backrun_simple(event.hash, *SIMPLE_CONTRACT_3)),
+            |ctx, event| Box::pin(backrun_simple(ctx, event.hash, *SIMPLE_CONTRACT_3)),
         )]
     }
 
-    pub fn handle_tx() -> Vec<(Predicate<EventTransaction>, HashHandler<EventTransaction>)> {
+    pub fn handle_tx() -> Vec<Strategy<EventTransaction, HashHandler<EventTransaction>>> {
         vec![
             (
+                "simple_contract_1",
+                Some(IndexKey::To(*SIMPLE_CONTRACT_1)),
                 |tx| {
                     tx.to == Some(*SIMPLE_CONTRACT_1)
                         && tx
@@ -46,9 +137,11 @@ impl Executor {
                             .map(|e| e.to_string() == "0xa3c356e4")
                             .unwrap_or_default()
                 },
-                |hash, tx| Box::pin(backrun_simple(hash, tx.to.unwrap())),
+                |ctx, hash, tx| Box::pin(backrun_simple(ctx, hash, tx.to.unwrap())),
             ),
             (
+                "simple_contract_2",
+                Some(IndexKey::To(*SIMPLE_CONTRACT_2)),
                 |tx| {
                     tx.to == Some(*SIMPLE_CONTRACT_2)
                         && tx
@@ -58,107 +151,267 @@ impl Executor {
                             .unwrap_or_default()
                         && tx.calldata.is_none()
                 },
-                |hash, tx| Box::pin(backrun_simple(hash, tx.to.unwrap())),
+                |ctx, hash, tx| Box::pin(backrun_simple(ctx, hash, tx.to.unwrap())),
             ),
         ]
     }
 
-    pub fn handle_log() -> Vec<(
-        Predicate<EventTransactionLog>,
-        HashHandler<EventTransactionLog>,
-    )> {
+    pub fn handle_log() -> Vec<Strategy<EventTransactionLog, HashHandler<EventTransactionLog>>> {
         vec![
             (
-                |log| {
-                    log.address == *SIMPLE_CONTRACT_4
-                        && log.topics.get(0).map(|t| *t == H256::from_str("0x59d3ce47d6ad6c6003cef97d136155b29d88653eb355c8bed6e03fbf694570ca").unwrap()).unwrap_or_default()
-                },
-                |hash, log| Box::pin(backrun_simple(hash, log.address)),
+                "simple_contract_4",
+                Some(IndexKey::Log(*SIMPLE_CONTRACT_4, *SIMPLE_ACTIVATE_TOPIC)),
+                |log| log.address == *SIMPLE_CONTRACT_4 && has_topic(log, *SIMPLE_ACTIVATE_TOPIC),
+                |ctx, hash, log| Box::pin(backrun_simple(ctx, hash, log.address)),
             ),
             (
+                "simple_contract_triple",
+                Some(IndexKey::Log(
+                    *SIMPLE_CONTRACT_TRIPLE,
+                    *SIMPLE_ACTIVATE_TOPIC,
+                )),
                 |log| {
-                    log.address == *SIMPLE_CONTRACT_TRIPLE
-                        && log.topics.get(0).map(|t| *t == H256::from_str("0x59d3ce47d6ad6c6003cef97d136155b29d88653eb355c8bed6e03fbf694570ca").unwrap()).unwrap_or_default()
+                    log.address == *SIMPLE_CONTRACT_TRIPLE && has_topic(log, *SIMPLE_ACTIVATE_TOPIC)
                 },
-                |hash, log| Box::pin(backrun_simple_triple(hash, log.address)),
+                |ctx, hash, log| Box::pin(backrun_simple_triple(ctx, hash, log.address)),
             ),
             (
-                |log| {
-                    log.address == *MAGIC_CONTRACT_1 && log.topics.get(0).map(|t| *t == H256::from_str("0x86a27c2047f889fafe51029e28e24f466422abe8a82c0c27de4683dda79a0b5d").unwrap()).unwrap_or_default()
-                },
-                |hash, log| {
-                    Box::pin(async move { backrun_magic_numba(hash, log.address, &log.data).await })
+                "magic_contract_1",
+                Some(IndexKey::Log(*MAGIC_CONTRACT_1, *MAGIC_ACTIVATE_TOPIC)),
+                |log| log.address == *MAGIC_CONTRACT_1 && has_topic(log, *MAGIC_ACTIVATE_TOPIC),
+                |ctx, hash, log| {
+                    Box::pin(
+                        async move { backrun_magic_numba(ctx, hash, log.address, &log.data).await },
+                    )
                 },
             ),
             (
-                |log| {
-                    log.address == *MAGIC_CONTRACT_2 && log.topics.get(0).map(|t| *t == H256::from_str("0x86a27c2047f889fafe51029e28e24f466422abe8a82c0c27de4683dda79a0b5d").unwrap()).unwrap_or_default()
-                },
-                |hash, log| {
-                    Box::pin(async move { backrun_magic_numba(hash, log.address, &log.data).await })
+                "magic_contract_2",
+                Some(IndexKey::Log(*MAGIC_CONTRACT_2, *MAGIC_ACTIVATE_TOPIC)),
+                |log| log.address == *MAGIC_CONTRACT_2 && has_topic(log, *MAGIC_ACTIVATE_TOPIC),
+                |ctx, hash, log| {
+                    Box::pin(
+                        async move { backrun_magic_numba(ctx, hash, log.address, &log.data).await },
+                    )
                 },
             ),
             (
-                |log| {
-                    log.address == *MAGIC_CONTRACT_3 && log.topics.get(0).map(|t| *t == H256::from_str("0x86a27c2047f889fafe51029e28e24f466422abe8a82c0c27de4683dda79a0b5d").unwrap()).unwrap_or_default()
-                },
-                |hash, log| {
-                    Box::pin(async move { backrun_magic_numba(hash, log.address, &log.data).await })
+                "magic_contract_3",
+                Some(IndexKey::Log(*MAGIC_CONTRACT_3, *MAGIC_ACTIVATE_TOPIC)),
+                |log| log.address == *MAGIC_CONTRACT_3 && has_topic(log, *MAGIC_ACTIVATE_TOPIC),
+                |ctx, hash, log| {
+                    Box::pin(
+                        async move { backrun_magic_numba(ctx, hash, log.address, &log.data).await },
+                    )
                 },
             ),
             (
+                "new_contract_addr",
+                Some(IndexKey::Log(
+                    *NEW_CONTRACT_CONTRACT,
+                    *NEW_CONTRACT_ACTIVATE_TOPIC,
+                )),
                 |log| {
-                    log.address == *NEW_CONTRACT_CONTRACT && log.topics.get(0).map(|t| *t == H256::from_str("0xf7e9fe69e1d05372bc855b295bc4c34a1a0a5882164dd2b26df30a26c1c8ba15").unwrap()).unwrap_or_default()
+                    log.address == *NEW_CONTRACT_CONTRACT
+                        && has_topic(log, *NEW_CONTRACT_ACTIVATE_TOPIC)
                 },
-                |hash, log| {
+                |ctx, hash, log| {
                     Box::pin(async move {
-                        backrun_create_contract_addr(hash, log.address, &log.data).await
+                        backrun_create_contract_addr(ctx, hash, log.address, &log.data).await
                     })
                 },
             ),
             (
+                "new_contract_salt",
+                Some(IndexKey::Log(
+                    *NEW_CONTRACT_CONTRACT,
+                    *NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC,
+                )),
                 |log| {
-                    log.address == *NEW_CONTRACT_CONTRACT && log.topics.get(0).map(|t| *t == H256::from_str("0x71fd33d3d871c60dc3d6ecf7c8e5bb086aeb6491528cce181c289a411582ff1c").unwrap()).unwrap_or_default()
+                    log.address == *NEW_CONTRACT_CONTRACT
+                        && has_topic(log, *NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC)
                 },
-                |hash, log| {
+                |ctx, hash, log| {
                     Box::pin(async move {
-                        backrun_create_contract_salt(hash, log.address, &log.data).await
+                        backrun_create_contract_salt(ctx, hash, log.address, &log.data).await
                     })
                 },
             ),
         ]
     }
 
-    pub fn execute_event(event: &Event) {
-        for (predicate, handler) in Self::handle_event() {
-            let event = event.clone();
-            tokio::spawn(async move {
-                if predicate(&event) {
-                    handler(event).await;
-                }
-            });
+    /// Matches an event against the strategy index inline and queues a job for each matching
+    /// handler only.
+    pub fn dispatch(ctx: Arc<AppContext>, event: Event) {
+        let _span = info_span!("event", hash = ?event.hash).entered();
+        ctx.metrics.observe_event(&event);
+        debug!(
+            txs = event.transactions.len(),
+            logs = event.logs.len(),
+            "Received event"
+        );
+
+        // only candidates get a job, most log hints come from contracts we know nothing about
+        for log in &event.logs {
+            if !ctx.discovery.is_candidate(log.address) {
+                continue;
+            }
+            let task_ctx = ctx.clone();
+            let log = log.clone();
+            let hash = event.hash;
+            ctx.queue.submit(
+                "discovery",
+                Box::pin(async move {
+                    task_ctx.discovery.observe_log(&log).await;
+                    if task_ctx.discovery.is_registered(log.address).await {
+                        backrun_simple(task_ctx.clone(), hash, log.address).await;
+                    }
+                }),
+            );
+        }
+
+        for (name, handler) in Self::match_strategies(&ctx, &event) {
+            ctx.queue.submit(name, handler);
         }
     }
 
-    pub fn execute_tx(hash: H256, tx: &EventTransaction) {
-        for (predicate, handler) in Self::handle_tx() {
-            let tx = tx.clone();
-            tokio::spawn(async move {
-                if predicate(&tx) {
-                    handler(hash, tx).await;
+    /// Runs a matched handler with its strategy set for the task and in a span named after it,
+    /// unless the strategy is paused.
+    pub fn run_strategy(
+        ctx: &Arc<AppContext>,
+        name: &'static str,
+        handler: BoxFuture<'static, ()>,
+    ) -> BoxFuture<'static, ()> {
+        ctx.metrics.matches.with_label_values(&[name]).inc();
+        let ctx = ctx.clone();
+        Box::pin(
+            async move {
+                if ctx.admin.is_paused(name).await {
+                    debug!("Strategy paused, skipping");
+                    return;
                 }
-            });
-        }
+                with_strategy(name, handler).await
+            }
+            .instrument(info_span!("strategy", strategy = name)),
+        )
     }
 
-    pub fn execute_log(hash: H256, log: &EventTransactionLog) {
-        for (predicate, handler) in Self::handle_log() {
-            let log = log.clone();
-            tokio::spawn(async move {
-                if predicate(&log) {
-                    handler(hash, log).await;
+    pub fn strategy_names() -> Vec<&'static str> {
+        let events = Self::handle_event().into_iter().map(|(name, ..)| name);
+        let txs = Self::handle_tx().into_iter().map(|(name, ..)| name);
+        let logs = Self::handle_log().into_iter().map(|(name, ..)| name);
+        events.chain(txs).chain(logs).collect()
+    }
+
+    /// Evaluates the strategies indexed for the event and its txs and logs, returning the names
+    /// of the matching strategies with their not yet started handlers.
+    pub fn match_event(
+        ctx: &Arc<AppContext>,
+        event: &Event,
+    ) -> Vec<(&'static str, BoxFuture<'static, ()>)> {
+        let _span = info_span!("event", hash = ?event.hash).entered();
+        Self::match_strategies(ctx, event)
+    }
+
+    fn match_strategies(
+        ctx: &Arc<AppContext>,
+        event: &Event,
+    ) -> Vec<(&'static str, BoxFuture<'static, ()>)> {
+        if ctx.config.pending_hints {
+            ctx.pending.observe(event);
+        }
+        let mut matched = vec![];
+        for (name, _, predicate, handler) in &STRATEGIES.events {
+            if predicate(event) {
+                matched.push((*name, handler(ctx.clone(), event.clone())));
+            }
+        }
+        for tx in &event.transactions {
+            for (name, _, predicate, handler) in STRATEGIES.txs(tx) {
+                if predicate(tx) {
+                    matched.push((*name, handler(ctx.clone(), event.hash, tx.clone())));
+                }
+            }
+        }
+        for log in &event.logs {
+            for (name, _, predicate, handler) in STRATEGIES.logs(log) {
+                if predicate(log) {
+                    matched.push((*name, handler(ctx.clone(), event.hash, log.clone())));
                 }
-            });
+            }
+        }
+        matched
+            .into_iter()
+            .map(|(name, handler)| (name, Self::run_strategy(ctx, name, handler)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers_core::{
+        types::{transaction::eip2718::TypedTransaction, Bytes, U256, U64},
+        utils::rlp::Rlp,
+    };
+    use futures_util::StreamExt;
+    use mev_share_rpc_api::BundleItem;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        budget::Budget,
+        config::Config,
+        sink::{MockBehaviour, MockRelay},
+        source::{ChannelSource, EventSource},
+    };
+
+    #[tokio::test]
+    async fn dispatches_events_into_bundles() {
+        let path = std::env::temp_dir().join("mev_share_ctf_executor_progress.json");
+        let _ = std::fs::remove_file(&path);
+        let relay = Arc::new(MockRelay::new(MockBehaviour::Accept));
+        let ctx = Arc::new(
+            AppContext::new(Config::for_tests(path), relay.clone())
+                .unwrap()
+                .with_budget(Budget::unlimited())
+                .with_fixed_nonce(U256::from(7)),
+        );
+        ctx.progress.set_latest_block(U64::from(100)).await;
+
+        let hash = H256::from_low_u64_be(1);
+        let event = serde_json::from_value::<Event>(json!({
+            "hash": hash,
+            "txs": [{
+                "to": *SIMPLE_CONTRACT_1,
+                "functionSelector": "0xa3c356e4",
+                "callData": "0xa3c356e4",
+            }],
+            "logs": null,
+        }))
+        .unwrap();
+        let (sender, source) = ChannelSource::new();
+        sender.send(event).unwrap();
+        drop(sender);
+        let mut events = source.events().await.unwrap();
+        while let Some(event) = events.next().await {
+            Executor::dispatch(ctx.clone(), event);
         }
+        ctx.tasks.close();
+        ctx.tasks.wait().await;
+
+        let bundles = relay.bundles();
+        assert_eq!(bundles.len(), 1);
+        assert_eq!(bundles[0].inclusion.block, U64::from(100));
+        let body = &bundles[0].bundle_body;
+        assert_eq!(body.len(), 2);
+        assert!(matches!(body[0], BundleItem::Hash { hash: h } if h == hash));
+        let BundleItem::Tx { tx, can_revert } = &body[1] else {
+            panic!("Expected our tx after the backrun hash, got {:?}", body[1]);
+        };
+        assert!(!can_revert);
+        let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(tx.as_ref())).unwrap();
+        assert_eq!(tx.to_addr(), Some(&*SIMPLE_CONTRACT_1));
+        assert_eq!(tx.nonce(), Some(&U256::from(7)));
+        assert_eq!(tx.data(), Some(&"0xb88a802f".parse::<Bytes>().unwrap()));
     }
 }
@@ -1,53 +1,76 @@
-use std::{error::Error, str::FromStr};
+use std::{error::Error, str::FromStr, sync::Arc, time::Instant};
 
 use ethers_core::{
-    abi::{RawLog, Token},
+    abi::{Abi, RawLog, Token},
     types::{Bytes, Eip1559TransactionRequest, H160, H256, U256},
 };
-use ethers_providers::Middleware;
-use ethers_signers::Signer;
-use futures_util::Future;
-use mev_share_rpc_api::{BundleItem, Inclusion, SendBundleRequest};
+use futures_util::{future::ready, stream, Future, StreamExt};
+use mev_share_rpc_api::{BundleItem, Inclusion, SendBundleRequest, SendBundleResponse};
+use tracing::{debug, error, field, info, info_span, Instrument, Span};
 
 use crate::{
+    admin::InFlightBundle,
+    backtest::{current_strategy, report_bundle, report_failure},
+    budget::bundle_nonces,
+    bundle::BundleBuilder,
+    bundle_options::validate_bundle,
     constants::{
-        BUNDLE_BLOCK_WINDOW, NEW_CONTRACT_INIT_CODE_HASH, PROGRESS, RELAY_CLIENT, RPC_CLIENT,
-        WALLET,
+        BUNDLE_BLOCK_WINDOW, MAGIC_ACTIVATE_TOPIC, NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC,
+        NEW_CONTRACT_ACTIVATE_TOPIC, NEW_CONTRACT_INIT_CODE,
     },
+    context::AppContext,
     contracts::{MAGIC_NUMBER_ABI, NEW_CONTRACT_ABI},
+    error::DecodeError,
+    nonce::next_nonce,
     signer::sign_transaction,
 };
 
-pub async fn backrun_create_contract_salt(tx_to_backrun: H256, to: H160, data: &Bytes) {
-    backrun_handler(tx_to_backrun, to, async move {
-        let nonce = RPC_CLIENT
-            .get_transaction_count(WALLET.address(), None)
-            .await?;
-        let salt = NEW_CONTRACT_ABI
-            .event("ActivateBySalt")?
-            .parse_log(RawLog {
-                topics: vec![H256::from_str(
-                    "0x71fd33d3d871c60dc3d6ecf7c8e5bb086aeb6491528cce181c289a411582ff1c",
-                )?],
+/// Decodes the params of a log hint against `event` of `abi`.
+fn decode_log(
+    abi: &Abi,
+    event: &str,
+    topic: H256,
+    data: &Bytes,
+) -> Result<Vec<Token>, DecodeError> {
+    abi.event(event)
+        .and_then(|e| {
+            e.parse_log(RawLog {
+                topics: vec![topic],
                 data: data.to_vec(),
-            })?
-            .params
-            .into_iter()
-            .next()
-            .unwrap()
-            .value
-            .into_fixed_bytes()
-            .unwrap();
+            })
+        })
+        .map(|log| log.params.into_iter().map(|p| p.value).collect())
+        .map_err(|e| DecodeError(format!("{} log: {}", event, e)))
+}
+
+pub async fn backrun_create_contract_salt(
+    ctx: Arc<AppContext>,
+    tx_to_backrun: H256,
+    to: H160,
+    data: &Bytes,
+) {
+    backrun_handler(ctx.clone(), tx_to_backrun, to, async move {
+        let nonce = next_nonce(&ctx).await?;
+        let salt = decode_log(
+            &NEW_CONTRACT_ABI,
+            "ActivateBySalt",
+            *NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC,
+            data,
+        )?
+        .into_iter()
+        .next()
+        .and_then(Token::into_fixed_bytes)
+        .ok_or_else(|| DecodeError("salt of ActivateBySalt".to_string()))?;
         let addr = ethers_core::utils::get_create2_address_from_hash(
             to,
             salt,
-            *NEW_CONTRACT_INIT_CODE_HASH,
+            ctx.init_code_hash(NEW_CONTRACT_INIT_CODE)?,
         );
         let tx = Eip1559TransactionRequest::new()
             .to(addr)
             .data(Bytes::from_str("0xb88a802f")?)
             .nonce(nonce);
-        let bytes = sign_transaction(tx).await?;
+        let bytes = sign_transaction(ctx.wallet(), tx).await?;
         Ok(vec![BundleItem::Tx {
             tx: bytes,
             can_revert: false,
@@ -56,31 +79,29 @@ pub async fn backrun_create_contract_salt(tx_to_backrun: H256, to: H160, data: &
     .await;
 }
 
-pub async fn backrun_create_contract_addr(tx_to_backrun: H256, to: H160, data: &Bytes) {
-    backrun_handler(tx_to_backrun, to, async move {
-        let nonce = RPC_CLIENT
-            .get_transaction_count(WALLET.address(), None)
-            .await?;
-        let addr = NEW_CONTRACT_ABI
-            .event("Activate")?
-            .parse_log(RawLog {
-                topics: vec![H256::from_str(
-                    "0xf7e9fe69e1d05372bc855b295bc4c34a1a0a5882164dd2b26df30a26c1c8ba15",
-                )?],
-                data: data.to_vec(),
-            })?
-            .params
-            .into_iter()
-            .next()
-            .unwrap()
-            .value
-            .into_address()
-            .unwrap();
+pub async fn backrun_create_contract_addr(
+    ctx: Arc<AppContext>,
+    tx_to_backrun: H256,
+    to: H160,
+    data: &Bytes,
+) {
+    backrun_handler(ctx.clone(), tx_to_backrun, to, async move {
+        let nonce = next_nonce(&ctx).await?;
+        let addr = decode_log(
+            &NEW_CONTRACT_ABI,
+            "Activate",
+            *NEW_CONTRACT_ACTIVATE_TOPIC,
+            data,
+        )?
+        .into_iter()
+        .next()
+        .and_then(Token::into_address)
+        .ok_or_else(|| DecodeError("address of Activate".to_string()))?;
         let tx = Eip1559TransactionRequest::new()
             .to(addr)
             .data(Bytes::from_str("0xb88a802f")?)
             .nonce(nonce);
-        let bytes = sign_transaction(tx).await?;
+        let bytes = sign_transaction(ctx.wallet(), tx).await?;
         Ok(vec![BundleItem::Tx {
             tx: bytes,
             can_revert: false,
@@ -89,30 +110,42 @@ pub async fn backrun_create_contract_addr(tx_to_backrun: H256, to: H160, data: &
     .await;
 }
 
-pub async fn backrun_magic_numba(tx_to_backrun: H256, to: H160, bound_data: &Bytes) {
+pub async fn backrun_magic_numba(
+    ctx: Arc<AppContext>,
+    tx_to_backrun: H256,
+    to: H160,
+    bound_data: &Bytes,
+) {
     if let Err(err) = async {
-        let nonce = RPC_CLIENT
-            .get_transaction_count(WALLET.address(), None)
-            .await?;
-
-        let mut bounds = MAGIC_NUMBER_ABI
-            .event("Activate")?
-            .parse_log(RawLog {
-                topics: vec![H256::from_str(
-                    "0x86a27c2047f889fafe51029e28e24f466422abe8a82c0c27de4683dda79a0b5d",
-                )?],
-                data: bound_data.to_vec(),
-            })?
-            .params
-            .into_iter();
-        let lower_b = bounds.next().unwrap().value.into_uint().unwrap();
-        let upper_b = bounds.next().unwrap().value.into_uint().unwrap();
-
-        let mut magic_number = lower_b;
-        while magic_number <= upper_b {
-            magic_number += U256::one();
-            tokio::spawn(async move {
-                backrun_handler(tx_to_backrun, to, async move {
+        let nonce = next_nonce(&ctx).await?;
+
+        let mut bounds = decode_log(
+            &MAGIC_NUMBER_ABI,
+            "Activate",
+            *MAGIC_ACTIVATE_TOPIC,
+            bound_data,
+        )?
+        .into_iter()
+        .map(Token::into_uint);
+        let (Some(Some(lower_b)), Some(Some(upper_b))) = (bounds.next(), bounds.next()) else {
+            return Err(DecodeError("bounds of Activate".to_string()).into());
+        };
+
+        // built lazily, the bounds come from the hint and may span any range
+        let candidates = stream::unfold(lower_b, move |magic_number| {
+            ready(if magic_number <= upper_b {
+                magic_number
+                    .checked_add(U256::one())
+                    .map(|next| (next, next))
+            } else {
+                None
+            })
+        })
+        .map(|magic_number| {
+            let ctx = ctx.clone();
+            async move {
+                backrun_handler(ctx.clone(), tx_to_backrun, to, async move {
+                    Span::current().record("nonce", nonce.as_u64());
                     let tx_body = Bytes::from(
                         MAGIC_NUMBER_ABI
                             .function("claimReward")?
@@ -122,36 +155,39 @@ pub async fn backrun_magic_numba(tx_to_backrun: H256, to: H160, bound_data: &Byt
                         .to(to)
                         .data(tx_body)
                         .nonce(nonce);
-                    let bytes = sign_transaction(tx).await?;
+                    let bytes = sign_transaction(ctx.wallet(), tx).await?;
                     Ok(vec![BundleItem::Tx {
                         tx: bytes,
                         can_revert: false,
                     }])
                 })
-                .await;
-            });
-        }
+                .await
+            }
+        });
+        candidates
+            .buffer_unordered(ctx.config.queue.candidate_concurrency)
+            .collect::<Vec<_>>()
+            .await;
 
         Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
     }
     .await
     {
-        println!("Error getting nonce: {:?}", err);
+        error!(contract = ?to, "Error preparing magic number candidates: {:?}", err);
+        report_failure(&ctx, err.as_ref());
     }
 }
 
-pub async fn backrun_simple_triple(tx_to_backrun: H256, to: H160) {
-    backrun_handler(tx_to_backrun, to, async move {
-        let nonce = RPC_CLIENT
-            .get_transaction_count(WALLET.address(), None)
-            .await?;
+pub async fn backrun_simple_triple(ctx: Arc<AppContext>, tx_to_backrun: H256, to: H160) {
+    backrun_handler(ctx.clone(), tx_to_backrun, to, async move {
+        let nonce = next_nonce(&ctx).await?;
         let mut bundle = vec![];
         for i in 0..3 {
             let tx = Eip1559TransactionRequest::new()
                 .to(to)
                 .data(Bytes::from_str("0xb88a802f")?)
                 .nonce(nonce + i);
-            let bytes = sign_transaction(tx).await?;
+            let bytes = sign_transaction(ctx.wallet(), tx).await?;
             bundle.push(BundleItem::Tx {
                 tx: bytes,
                 can_revert: false,
@@ -162,64 +198,154 @@ pub async fn backrun_simple_triple(tx_to_backrun: H256, to: H160) {
     .await
 }
 
-pub async fn backrun_simple(tx_to_backrun: H256, to: H160) {
-    backrun_handler(tx_to_backrun, to, async move {
-        let nonce = RPC_CLIENT
-            .get_transaction_count(WALLET.address(), None)
-            .await?;
+pub async fn backrun_simple(ctx: Arc<AppContext>, tx_to_backrun: H256, to: H160) {
+    let task_ctx = ctx.clone();
+    let items = async move {
+        let nonce = next_nonce(&task_ctx).await?;
         let tx = Eip1559TransactionRequest::new()
             .to(to)
             .data(Bytes::from_str("0xb88a802f")?)
             .nonce(nonce);
-        let bytes = sign_transaction(tx).await?;
+        let bytes = sign_transaction(task_ctx.wallet(), tx).await?;
         Ok(vec![BundleItem::Tx {
             tx: bytes,
             can_revert: false,
         }])
-    })
-    .await
+    };
+    // one claim backruns every pending activation of the contract
+    if ctx.config.pending_hints {
+        backrun_pending(ctx, tx_to_backrun, to, items).await
+    } else {
+        backrun_handler(ctx, tx_to_backrun, to, items).await
+    }
+}
+
+/// Backruns `tx_to_backrun` with the txs built by `items`, unless `to` is already captured.
+pub async fn backrun_handler<
+    O: Future<Output = Result<Vec<BundleItem>, Box<dyn Error + Send + Sync>>>,
+>(
+    ctx: Arc<AppContext>,
+    tx_to_backrun: H256,
+    to: H160,
+    items: O,
+) {
+    let targets = BundleBuilder::new().backrun(tx_to_backrun);
+    backrun_targets(ctx, targets, to, items).await
 }
 
-async fn backrun_handler<
+/// Like `backrun_handler`, also backrunning every other pending tx seen touching `to` in the
+/// same bundle, oldest first. Used by `backrun_simple` when `PENDING_HINTS` is set.
+pub async fn backrun_pending<
     O: Future<Output = Result<Vec<BundleItem>, Box<dyn Error + Send + Sync>>>,
 >(
+    ctx: Arc<AppContext>,
     tx_to_backrun: H256,
     to: H160,
     items: O,
 ) {
-    if PROGRESS.get_progress_for_address(to).await {
-        //println!("Skipping address {}: Already processed", to);
+    let targets = ctx
+        .pending
+        .pending_for(to)
+        .into_iter()
+        .filter(|hash| *hash != tx_to_backrun)
+        .fold(BundleBuilder::new(), BundleBuilder::backrun)
+        .backrun(tx_to_backrun);
+    backrun_targets(ctx, targets, to, items).await
+}
+
+/// Backruns the pending txs and nested bundles already in `targets` with the txs built by
+/// `items`, unless `to` is already captured.
+pub async fn backrun_targets<
+    O: Future<Output = Result<Vec<BundleItem>, Box<dyn Error + Send + Sync>>>,
+>(
+    ctx: Arc<AppContext>,
+    targets: BundleBuilder,
+    to: H160,
+    items: O,
+) {
+    if ctx.progress.get_progress_for_address(to).await {
+        debug!(contract = ?to, "Skipping contract, already captured");
         return;
     }
 
-    println!(
-        "Processing transaction to {:?} backrunning {:?}!",
-        to, tx_to_backrun
+    let span = info_span!(
+        "bundle",
+        contract = ?to,
+        backrun = ?targets.targets(),
+        strategy = current_strategy().unwrap_or("unknown"),
+        nonce = field::Empty,
+        target_block = field::Empty,
+        relay = ctx.sink.name(),
     );
-    PROGRESS.set_is_processing(to, true).await;
-
-    if let Err(e) = async {
-        let mut bundle_body = vec![BundleItem::Hash {
-            hash: tx_to_backrun,
-        }];
-        bundle_body.append(&mut items.await?.to_vec());
-        let block = PROGRESS.get_latest_block().await;
-        let bundle = SendBundleRequest {
-            bundle_body,
-            inclusion: Inclusion {
+    async {
+        info!("Processing transaction");
+        let _processing = ctx.progress.start_processing(to);
+
+        if let Err(e) = async {
+            ctx.budget.check_balance()?;
+            let backruns = targets.targets();
+            let block = ctx.progress.get_latest_block().await;
+            Span::current().record("target_block", block.as_u64());
+            let mut bundle = targets.items(items.await?).build(Inclusion {
                 block,
                 max_block: Some(block + BUNDLE_BLOCK_WINDOW),
-            },
-            ..Default::default()
-        };
-
-        let resp = RELAY_CLIENT.as_ref().send_bundle(bundle.clone()).await?;
-        println!("Got a bundle response: {:?}", resp);
+            })?;
+            let options = ctx.config.bundle_options(current_strategy());
+            bundle.validity = options.validity();
+            bundle.privacy = options.privacy();
+            report_bundle(&ctx, &bundle);
+            let resp = send_checked(&ctx, &bundle).await?;
+            info!(bundle_hash = ?resp.bundle_hash, "Bundle sent");
+            ctx.admin
+                .track(
+                    resp.bundle_hash,
+                    InFlightBundle {
+                        strategy: current_strategy(),
+                        contract: to,
+                        backruns,
+                        relay: ctx.sink.name().to_string(),
+                        block: bundle.inclusion.block,
+                        max_block: bundle.inclusion.max_block.unwrap_or(block),
+                        request: bundle,
+                    },
+                )
+                .await;
 
-        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+            Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+        }
+        .await
+        {
+            error!("Error processing contract: {:?}", e);
+            report_failure(&ctx, e.as_ref());
+        }
     }
+    .instrument(span)
     .await
-    {
-        println!("Error processing address {}: {:?}", to, e);
-    }
+}
+
+/// Sends a bundle through the context's sink once it passes `validate_bundle` and the spend
+/// budget, counting it in the relay metrics.
+pub async fn send_checked(
+    ctx: &AppContext,
+    bundle: &SendBundleRequest,
+) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>> {
+    validate_bundle(bundle)?;
+    let nonces = bundle_nonces(&bundle.bundle_body)?;
+    ctx.budget.reserve(&nonces)?;
+    let relay = ctx.sink.name();
+    ctx.metrics.bundles_built.with_label_values(&[relay]).inc();
+
+    let started = Instant::now();
+    let resp = ctx.sink.send_bundle(bundle.clone()).await;
+    ctx.metrics
+        .relay_latency
+        .with_label_values(&[relay])
+        .observe(started.elapsed().as_secs_f64());
+    let resp = resp.map_err(|e| {
+        ctx.metrics.bundles_failed.with_label_values(&[relay]).inc();
+        ctx.budget.release(&nonces);
+        e
+    })?;
+    ctx.metrics.bundles_sent.with_label_values(&[relay]).inc();
+    Ok(resp)
 }
@@ -0,0 +1,86 @@
+use std::{collections::HashMap, error::Error, path::Path, str::FromStr};
+
+use ethers_core::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Abi,
+    },
+    types::{Bytes, H256},
+    utils::keccak256,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InitCodeConfig {
+    /// Path to a solc, hardhat or foundry JSON artifact.
+    pub artifact: String,
+    /// Constructor arguments in their human readable form, encoded against the artifact's ABI.
+    #[serde(default)]
+    pub constructor_args: Vec<String>,
+}
+
+pub fn read_init_code_hashes(path: &Path) -> Result<HashMap<String, H256>, Box<dyn Error>> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read init codes {}: {}", path.display(), e))?;
+    let configs = serde_json::from_str::<HashMap<String, InitCodeConfig>>(&data)?;
+    configs
+        .into_iter()
+        .map(|(name, config)| {
+            let (abi, bytecode) = read_artifact(&config.artifact)?;
+            let code = encode_init_code(&abi, bytecode, &config.constructor_args)?;
+            Ok((name, H256(keccak256(code))))
+        })
+        .collect()
+}
+
+pub fn read_artifact(path: &str) -> Result<(Abi, Bytes), Box<dyn Error>> {
+    let artifact = serde_json::from_str::<Value>(&std::fs::read_to_string(path)?)?;
+    let abi = match artifact.get("abi") {
+        Some(abi) => serde_json::from_value::<Abi>(abi.clone())?,
+        None => Abi::default(),
+    };
+    // foundry nests the bytecode under `object`, hardhat keeps it as a plain string and the solc
+    // standard json output puts it under `evm.bytecode.object`
+    let bytecode = artifact
+        .get("bytecode")
+        .and_then(|b| b.get("object").or(Some(b)))
+        .and_then(Value::as_str)
+        .or_else(|| {
+            artifact
+                .pointer("/evm/bytecode/object")
+                .and_then(Value::as_str)
+        })
+        .ok_or_else(|| format!("Cannot find bytecode in artifact {}", path))?;
+    Ok((abi, Bytes::from_str(bytecode)?))
+}
+
+pub fn encode_init_code(
+    abi: &Abi,
+    bytecode: Bytes,
+    args: &[String],
+) -> Result<Bytes, Box<dyn Error>> {
+    match &abi.constructor {
+        Some(constructor) => {
+            if constructor.inputs.len() != args.len() {
+                return Err(format!(
+                    "Expected {} constructor arguments, got {}",
+                    constructor.inputs.len(),
+                    args.len()
+                )
+                .into());
+            }
+            let tokens = constructor
+                .inputs
+                .iter()
+                .zip(args)
+                .map(|(param, arg)| LenientTokenizer::tokenize(&param.kind, arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Bytes::from(
+                constructor.encode_input(bytecode.to_vec(), &tokens)?,
+            ))
+        }
+        None if args.is_empty() => Ok(bytecode),
+        None => Err("Constructor arguments given but artifact has no constructor".into()),
+    }
+}
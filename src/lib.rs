@@ -0,0 +1,44 @@
+//! MEV-Share CTF engine: strategies matched against MEV-Share events, handlers building and
+//! signing backrun bundles, and the progress store tracking captured challenges.
+//!
+//! Build a [`Config`], wrap it in an [`AppContext`] with a [`BundleSink`], then feed events
+//! from any [`EventSource`] to [`Executor::dispatch`] or run the whole loop with
+//! [`commands::run`].
+
+pub mod admin;
+pub mod backtest;
+pub mod budget;
+pub mod bundle;
+pub mod bundle_options;
+pub mod capture;
+pub mod client;
+pub mod commands;
+pub mod config;
+pub mod constants;
+pub mod context;
+pub mod contracts;
+pub mod discovery;
+pub mod error;
+pub mod executor;
+pub mod handler;
+pub mod init_code;
+pub mod logging;
+pub mod metrics;
+#[cfg(feature = "test-support")]
+pub mod mock_sse;
+pub mod nonce;
+pub mod pending;
+pub mod progress;
+pub mod queue;
+pub mod reconcile;
+pub mod recorder;
+pub mod relay;
+pub mod rpc;
+pub mod shutdown;
+pub mod signer;
+pub mod sink;
+pub mod source;
+
+pub use crate::{
+    config::Config, context::AppContext, executor::Executor, sink::BundleSink, source::EventSource,
+};
@@ -0,0 +1,17 @@
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Installs the global tracing subscriber, filtered by `RUST_LOG` (`info` if unset) and printing
+/// either human readable lines or one JSON object per event.
+pub fn init_tracing(json: bool) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = fmt().with_env_filter(filter);
+    if json {
+        builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .init();
+    } else {
+        builder.init();
+    }
+}
@@ -1,93 +1,57 @@
-use std::error::Error;
-
-use ethers_core::types::Block;
-use ethers_providers::{Middleware, Provider};
-use ethers_signers::Signer;
-use futures_util::StreamExt;
-use mev_share_sse::Event;
-
-use crate::{
-    constants::{CONTRACTS, EVENT_CLIENT, PROGRESS, RPC_CLIENT, SSE, WALLET, WS_URL},
-    executor::Executor,
+use std::{error::Error, sync::Arc};
+
+use clap::Parser;
+use mev_share_ctf_rs::{
+    client::Client,
+    commands,
+    config::{progress_path, Config},
+    context::AppContext,
+    logging::init_tracing,
+    progress::Progress,
+    recorder::Recorder,
+    sink::{BundleSink, FileSink},
+    source::SseSource,
 };
 
-pub mod client;
-pub mod constants;
-pub mod contracts;
-pub mod error;
-pub mod executor;
-pub mod handler;
-pub mod progress;
-pub mod signer;
+use crate::cli::{Cli, Command, LogFormat};
+
+mod cli;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    println!("Interacting with wallet: {:?}", WALLET.address());
-
-    let f1 = async {
-        let client = Provider::connect(&*WS_URL)
-            .await
-            .expect("Could not connect to WS endpoint");
-        let mut stream = client.subscribe_blocks().await?;
-        while let Some(Block {
-            hash: Some(hash),
-            number: Some(number),
-            timestamp,
-            ..
-        }) = stream.next().await
-        {
-            println!("Got block {}: {:?} at {:?}", number, hash, timestamp);
-            PROGRESS.set_latest_block(number).await;
-
-            if let Some(block) = RPC_CLIENT.get_block_with_txs(hash).await? {
-                block.transactions.iter().for_each(|tx| {
-                    if let Some(to) = &tx.to {
-                        let tx = tx.clone();
-                        let to = *to;
-                        tokio::spawn(async move {
-                            if CONTRACTS.contains(&to) && tx.from == WALLET.address() {
-                                println!("Found tx sent: {:?}", tx.hash);
-                                PROGRESS.add_progress_for_address(to).await;
-                            }
-                        });
-                    }
-                });
-            }
-        }
-        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
+    let cli = Cli::parse();
+    init_tracing(cli.log_format == LogFormat::Json);
+    let dry_run_sink = || -> Result<Arc<dyn BundleSink>, Box<dyn Error>> {
+        Ok(Arc::new(FileSink::new(cli.dry_run_out.as_deref())?))
     };
-    let f2 = async {
-        let mut stream = EVENT_CLIENT.events(SSE).await?;
-        println!("Subscribed to {}", stream.endpoint());
-
-        while let Some(Ok(event)) = stream.next().await {
-            Executor::execute_event(&event);
-
-            let Event {
-                hash,
-                transactions,
-                logs,
-            } = event;
-
-            for tx in transactions {
-                tokio::spawn(async move {
-                    Executor::execute_tx(hash, &tx);
-                });
-            }
-
-            for log in logs {
-                tokio::spawn(async move {
-                    Executor::execute_log(hash, &log);
-                });
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => {
+            let config = Config::from_env()?;
+            let sink: Arc<dyn BundleSink> = if cli.dry_run {
+                dry_run_sink()?
+            } else {
+                Arc::new(Client::new(&config.relay_url, &config.relay_limits)?)
+            };
+            let mut ctx = AppContext::new(config, sink)?;
+            if let Some(path) = &cli.record {
+                ctx = ctx.with_recorder(Recorder::open(path)?);
             }
+            let source = SseSource::new(ctx.event_client.clone(), &ctx.config.sse_url);
+            commands::run(Arc::new(ctx), &source).await
         }
-
-        Result::<(), Box<dyn Error + Send + Sync>>::Ok(())
-    };
-    let (r1, r2) = tokio::join!(f1, f2);
-
-    r1.expect("WebSocket failed");
-    r2.expect("Event stream failed");
-
-    Ok(())
+        Command::Status => commands::status(&progress_path()),
+        Command::Reset { contract, wallet } => commands::reset(&progress_path(), contract, wallet),
+        Command::Replay { file, speed } => {
+            let ctx = AppContext::new(Config::from_env()?, dry_run_sink()?)?;
+            // replayed captures and gas stay in memory, like the bundles
+            let wallet = ctx.address();
+            let ctx = ctx.with_progress(Progress::new(wallet));
+            commands::replay(Arc::new(ctx), &file, speed).await
+        }
+        Command::Backtest { file } => commands::backtest(Config::from_env()?, &file).await,
+        Command::Simulate { event } => {
+            let ctx = AppContext::new(Config::from_env()?, dry_run_sink()?)?;
+            commands::simulate(Arc::new(ctx), &event).await
+        }
+    }
 }
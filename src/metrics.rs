@@ -0,0 +1,224 @@
+use std::{convert::Infallible, error::Error, net::SocketAddr};
+
+use ethers_core::{types::U256, utils::format_ether};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use mev_share_sse::Event;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::{progress::Progress, recorder::now_millis};
+
+/// Prometheus metrics of one `AppContext`, each context has its own registry.
+#[derive(Clone)]
+pub struct Metrics {
+    pub registry: Registry,
+    /// SSE events received, by `type` (`hash_only`, `tx` or `log`).
+    pub events: IntCounterVec,
+    /// Predicate matches by `strategy`.
+    pub matches: IntCounterVec,
+    pub bundles_built: IntCounterVec,
+    pub bundles_sent: IntCounterVec,
+    pub bundles_failed: IntCounterVec,
+    /// Time taken by the relay to answer `mev_sendBundle`, by `relay`.
+    pub relay_latency: HistogramVec,
+    /// Seconds between the latest block's timestamp and the time we received it.
+    pub block_lag: IntGauge,
+    pub completed_contracts: IntGaugeVec,
+    /// Gas paid in ETH by our landed txs, by `wallet`.
+    pub gas_paid: GaugeVec,
+    /// Jobs dropped because the work queue was full, by `strategy`.
+    pub work_dropped: IntCounterVec,
+    /// Time jobs waited for a worker and their strategy's limit, by `strategy`.
+    pub work_delay: HistogramVec,
+    pub work_pending: IntGauge,
+    /// Wallet balance in ETH as of the latest block.
+    pub balance: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("mev_share_ctf".to_string()), None)?;
+        let metrics = Self {
+            events: IntCounterVec::new(
+                Opts::new("events_received_total", "SSE events received per type"),
+                &["type"],
+            )?,
+            matches: IntCounterVec::new(
+                Opts::new("strategy_matches_total", "Predicate matches per strategy"),
+                &["strategy"],
+            )?,
+            bundles_built: IntCounterVec::new(
+                Opts::new("bundles_built_total", "Bundles built per relay"),
+                &["relay"],
+            )?,
+            bundles_sent: IntCounterVec::new(
+                Opts::new("bundles_sent_total", "Bundles accepted by the relay"),
+                &["relay"],
+            )?,
+            bundles_failed: IntCounterVec::new(
+                Opts::new(
+                    "bundles_failed_total",
+                    "Bundles rejected or not sent per relay",
+                ),
+                &["relay"],
+            )?,
+            relay_latency: HistogramVec::new(
+                HistogramOpts::new("relay_latency_seconds", "Relay response time per relay"),
+                &["relay"],
+            )?,
+            block_lag: IntGauge::new("block_lag_seconds", "Lag of the latest block")?,
+            completed_contracts: IntGaugeVec::new(
+                Opts::new("completed_contracts", "Captured contracts per wallet"),
+                &["wallet"],
+            )?,
+            gas_paid: GaugeVec::new(
+                Opts::new("gas_paid_eth", "Gas paid by landed txs per wallet"),
+                &["wallet"],
+            )?,
+            work_dropped: IntCounterVec::new(
+                Opts::new("work_dropped_total", "Jobs dropped with the queue full"),
+                &["strategy"],
+            )?,
+            work_delay: HistogramVec::new(
+                HistogramOpts::new("work_delay_seconds", "Time jobs waited in the queue"),
+                &["strategy"],
+            )?,
+            work_pending: IntGauge::new("work_pending", "Jobs waiting or running")?,
+            balance: Gauge::new("wallet_balance_eth", "Wallet balance of the latest block")?,
+            registry,
+        };
+        metrics
+            .registry
+            .register(Box::new(metrics.events.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.matches.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bundles_built.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bundles_sent.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.bundles_failed.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.relay_latency.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.block_lag.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.completed_contracts.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.gas_paid.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.work_dropped.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.work_delay.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.work_pending.clone()))?;
+        metrics
+            .registry
+            .register(Box::new(metrics.balance.clone()))?;
+        Ok(metrics)
+    }
+
+    pub fn observe_event(&self, event: &Event) {
+        if event.transactions.is_empty() && event.logs.is_empty() {
+            self.events.with_label_values(&["hash_only"]).inc();
+        }
+        if !event.transactions.is_empty() {
+            self.events.with_label_values(&["tx"]).inc();
+        }
+        if !event.logs.is_empty() {
+            self.events.with_label_values(&["log"]).inc();
+        }
+    }
+
+    pub fn observe_block(&self, timestamp: U256) {
+        let now = now_millis() / 1000;
+        self.block_lag
+            .set(now.saturating_sub(timestamp.low_u64()) as i64);
+    }
+
+    pub fn observe_balance(&self, balance: U256) {
+        self.balance
+            .set(format_ether(balance).parse().unwrap_or_default());
+    }
+
+    pub async fn observe_progress(&self, progress: &Progress) {
+        for (wallet, completed) in progress.inner.read().await.iter() {
+            self.completed_contracts
+                .with_label_values(&[&format!("{:?}", wallet)])
+                .set(completed.len() as i64);
+        }
+        for (wallet, paid) in progress.gas_paid.read().await.iter() {
+            let total = paid.values().fold(U256::zero(), |total, p| total + *p);
+            self.gas_paid
+                .with_label_values(&[&format!("{:?}", wallet)])
+                .set(format_ether(total).parse().unwrap_or_default());
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, prometheus::Error> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// Serves the metrics in the Prometheus text format on `/metrics`.
+pub fn serve_metrics(
+    metrics: Metrics,
+    addr: SocketAddr,
+) -> Result<JoinHandle<()>, Box<dyn Error + Send + Sync>> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let metrics = metrics.clone();
+                async move { Ok::<_, Infallible>(metrics_response(&metrics, &req)) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    info!(addr = %server.local_addr(), "Serving metrics");
+    Ok(tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Metrics server failed: {:?}", e);
+        }
+    }))
+}
+
+fn metrics_response(metrics: &Metrics, req: &Request<Body>) -> Response<Body> {
+    if req.uri().path() != "/metrics" {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap();
+    }
+    match metrics.encode() {
+        Ok(body) => Response::builder()
+            .header(CONTENT_TYPE, TextEncoder::new().format_type())
+            .body(Body::from(body))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(e.to_string()))
+            .unwrap(),
+    }
+}
@@ -0,0 +1,170 @@
+use std::{convert::Infallible, error::Error, net::SocketAddr, sync::Arc, time::Duration};
+
+use ethers_core::{
+    abi::{encode, Token},
+    types::{Bytes, H160, H256, U256},
+};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::constants::{
+    MAGIC_ACTIVATE_TOPIC, MAGIC_CONTRACT_1, MAGIC_CONTRACT_2, MAGIC_CONTRACT_3,
+    NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC, NEW_CONTRACT_ACTIVATE_TOPIC, NEW_CONTRACT_CONTRACT,
+    SIMPLE_ACTIVATE_TOPIC, SIMPLE_CONTRACT_1, SIMPLE_CONTRACT_2, SIMPLE_CONTRACT_4,
+    SIMPLE_CONTRACT_TRIPLE,
+};
+
+/// A local HTTP server speaking the MEV-Share SSE protocol. Every client connecting gets the
+/// scripted events one `interval` apart, then keep-alive comments until it disconnects.
+pub struct MockSseServer {
+    pub url: String,
+    handle: JoinHandle<()>,
+}
+
+impl MockSseServer {
+    pub async fn start(
+        script: Vec<Value>,
+        interval: Duration,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let script = Arc::new(script);
+        let make_service = make_service_fn(move |_| {
+            let script = script.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                    let script = script.clone();
+                    async move { Ok::<_, Infallible>(stream_script(script, interval)) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("Mock SSE server failed: {:?}", e);
+            }
+        });
+        Ok(Self { url, handle })
+    }
+}
+
+impl Drop for MockSseServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn stream_script(script: Arc<Vec<Value>>, interval: Duration) -> Response<Body> {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for event in script.iter() {
+            tokio::time::sleep(interval).await;
+            let chunk = hyper::body::Bytes::from(format!("data: {}\n\n", event));
+            if sender.send_data(chunk).await.is_err() {
+                return;
+            }
+        }
+        // keep the stream open like the real endpoint does
+        while sender
+            .send_data(hyper::body::Bytes::from_static(b":\n\n"))
+            .await
+            .is_ok()
+        {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .body(body)
+        .unwrap()
+}
+
+/// An event for a fully private tx, only its hash is visible.
+pub fn hash_only_event(hash: H256) -> Value {
+    json!({ "hash": hash, "txs": null, "logs": null })
+}
+
+pub fn tx_event(hash: H256, to: H160, selector: Option<&str>, calldata: Option<&str>) -> Value {
+    json!({
+        "hash": hash,
+        "txs": [{ "to": to, "functionSelector": selector, "callData": calldata }],
+        "logs": null,
+    })
+}
+
+pub fn log_event(hash: H256, address: H160, topics: Vec<H256>, data: Bytes) -> Value {
+    json!({
+        "hash": hash,
+        "txs": null,
+        "logs": [{ "address": address, "topics": topics, "data": data }],
+    })
+}
+
+/// One event matching each CTF strategy in `executor.rs`, in order.
+pub fn ctf_script() -> Vec<Value> {
+    let bounds = Bytes::from(encode(&[
+        Token::Uint(U256::from(100)),
+        Token::Uint(U256::from(102)),
+    ]));
+    let hash = H256::from_low_u64_be;
+
+    vec![
+        tx_event(
+            hash(1),
+            *SIMPLE_CONTRACT_1,
+            Some("0xa3c356e4"),
+            Some("0xa3c356e4"),
+        ),
+        tx_event(hash(2), *SIMPLE_CONTRACT_2, Some("0xa3c356e4"), None),
+        hash_only_event(hash(3)),
+        log_event(
+            hash(4),
+            *SIMPLE_CONTRACT_4,
+            vec![*SIMPLE_ACTIVATE_TOPIC],
+            Bytes::new(),
+        ),
+        log_event(
+            hash(5),
+            *SIMPLE_CONTRACT_TRIPLE,
+            vec![*SIMPLE_ACTIVATE_TOPIC],
+            Bytes::new(),
+        ),
+        log_event(
+            hash(6),
+            *MAGIC_CONTRACT_1,
+            vec![*MAGIC_ACTIVATE_TOPIC],
+            bounds.clone(),
+        ),
+        log_event(
+            hash(7),
+            *MAGIC_CONTRACT_2,
+            vec![*MAGIC_ACTIVATE_TOPIC],
+            bounds.clone(),
+        ),
+        log_event(
+            hash(8),
+            *MAGIC_CONTRACT_3,
+            vec![*MAGIC_ACTIVATE_TOPIC],
+            bounds,
+        ),
+        log_event(
+            hash(9),
+            *NEW_CONTRACT_CONTRACT,
+            vec![*NEW_CONTRACT_ACTIVATE_TOPIC],
+            Bytes::from(encode(&[Token::Address(H160::from_low_u64_be(0xc7f))])),
+        ),
+        log_event(
+            hash(10),
+            *NEW_CONTRACT_CONTRACT,
+            vec![*NEW_CONTRACT_ACTIVATE_BY_SALT_TOPIC],
+            Bytes::from(encode(&[Token::FixedBytes(
+                H256::from_low_u64_be(0x5a17).as_bytes().to_vec(),
+            )])),
+        ),
+    ]
+}
@@ -0,0 +1,17 @@
+use std::error::Error;
+
+use ethers_core::types::U256;
+use ethers_providers::Middleware;
+use tracing::Span;
+
+use crate::context::AppContext;
+
+/// Fetches the nonce the next tx signed by the context's wallet should use.
+pub async fn next_nonce(ctx: &AppContext) -> Result<U256, Box<dyn Error + Send + Sync>> {
+    let nonce = match ctx.fixed_nonce {
+        Some(nonce) => nonce,
+        None => ctx.rpc.get_transaction_count(ctx.address(), None).await?,
+    };
+    Span::current().record("nonce", nonce.as_u64());
+    Ok(nonce)
+}
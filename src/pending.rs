@@ -0,0 +1,159 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use ethers_core::types::{Block, Transaction, H160, H256, U64};
+use mev_share_sse::Event;
+
+use crate::constants::BUNDLE_BLOCK_WINDOW;
+
+struct PendingHint {
+    hash: H256,
+    /// Tx recipients and log addresses of the hint.
+    addresses: Vec<H160>,
+    /// First block after the hint was seen, unset until then.
+    block: Option<U64>,
+}
+
+#[derive(Default)]
+struct Hints {
+    /// Oldest first.
+    hints: Vec<PendingHint>,
+    hashes: HashSet<H256>,
+}
+
+/// MEV-Share txs seen on the event stream that have not landed yet, in the order they were
+/// seen, so a bundle can backrun several of them. Forgotten once they land or
+/// `BUNDLE_BLOCK_WINDOW` blocks after they were seen.
+#[derive(Default)]
+pub struct PendingHints {
+    hints: Mutex<Hints>,
+}
+
+impl PendingHints {
+    pub fn observe(&self, event: &Event) {
+        let addresses = event
+            .transactions
+            .iter()
+            .filter_map(|tx| tx.to)
+            .chain(event.logs.iter().map(|log| log.address))
+            .collect::<Vec<_>>();
+        let mut hints = self.hints.lock().unwrap();
+        if !hints.hashes.insert(event.hash) {
+            return;
+        }
+        hints.hints.push(PendingHint {
+            hash: event.hash,
+            addresses,
+            block: None,
+        });
+    }
+
+    /// Hashes of the pending txs sent to or emitting logs from `address`, oldest first.
+    pub fn pending_for(&self, address: H160) -> Vec<H256> {
+        self.hints
+            .lock()
+            .unwrap()
+            .hints
+            .iter()
+            .filter(|h| h.addresses.contains(&address))
+            .map(|h| h.hash)
+            .collect()
+    }
+
+    /// Forgets the hints that landed in `block` or expired by then.
+    pub fn on_block(&self, block: &Block<Transaction>) {
+        let Some(number) = block.number else {
+            return;
+        };
+        let landed = block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash)
+            .collect::<HashSet<_>>();
+        let Hints { hints, hashes } = &mut *self.hints.lock().unwrap();
+        hints.retain_mut(|h| {
+            let seen = *h.block.get_or_insert(number);
+            let keep = seen + BUNDLE_BLOCK_WINDOW >= number && !landed.contains(&h.hash);
+            if !keep {
+                hashes.remove(&h.hash);
+            }
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn event(hash: u64, to: H160) -> Event {
+        serde_json::from_value(json!({
+            "hash": H256::from_low_u64_be(hash),
+            "txs": [{ "to": to, "functionSelector": null, "callData": null }],
+            "logs": null,
+        }))
+        .unwrap()
+    }
+
+    fn block(number: u64, landed: &[u64]) -> Block<Transaction> {
+        Block {
+            number: Some(number.into()),
+            transactions: landed
+                .iter()
+                .map(|hash| Transaction {
+                    hash: H256::from_low_u64_be(*hash),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tracks_hints_by_address_oldest_first() {
+        let (contract, other) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let pending = PendingHints::default();
+        pending.observe(&event(1, contract));
+        pending.observe(&event(2, other));
+        pending.observe(&event(3, contract));
+        pending.observe(&event(1, contract));
+
+        assert_eq!(
+            pending.pending_for(contract),
+            vec![H256::from_low_u64_be(1), H256::from_low_u64_be(3)]
+        );
+        assert_eq!(pending.pending_for(other), vec![H256::from_low_u64_be(2)]);
+    }
+
+    #[test]
+    fn forgets_landed_hints() {
+        let contract = H160::from_low_u64_be(1);
+        let pending = PendingHints::default();
+        pending.observe(&event(1, contract));
+        pending.observe(&event(2, contract));
+
+        pending.on_block(&block(10, &[1]));
+        assert_eq!(
+            pending.pending_for(contract),
+            vec![H256::from_low_u64_be(2)]
+        );
+
+        // a landed hint seen again is tracked anew
+        pending.observe(&event(1, contract));
+        assert_eq!(pending.pending_for(contract).len(), 2);
+    }
+
+    #[test]
+    fn forgets_expired_hints() {
+        let contract = H160::from_low_u64_be(1);
+        let pending = PendingHints::default();
+        pending.observe(&event(1, contract));
+
+        pending.on_block(&block(10, &[]));
+        pending.on_block(&block(10 + BUNDLE_BLOCK_WINDOW, &[]));
+        assert_eq!(pending.pending_for(contract).len(), 1);
+        pending.on_block(&block(11 + BUNDLE_BLOCK_WINDOW, &[]));
+        assert!(pending.pending_for(contract).is_empty());
+    }
+}
@@ -1,40 +1,74 @@
 use std::{
     collections::{HashMap, HashSet},
     error::Error,
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock as StdRwLock},
 };
 
-use ethers_core::types::{H160, U64};
-use ethers_signers::Signer;
-use tokio::sync::RwLock;
+use ethers_core::types::{H160, U256, U64};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{error, info};
 
-use crate::constants::WALLET;
+/// Gas paid in wei by each wallet, per contract.
+pub type GasPaid = HashMap<H160, HashMap<H160, U256>>;
+
+/// Contents of `ctf_progress.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProgressFile {
+    /// Captured contracts per wallet.
+    pub completed: HashMap<H160, HashSet<H160>>,
+    #[serde(default)]
+    pub gas_paid: GasPaid,
+}
+
+/// Files written before gas was recorded only hold the captured contracts per wallet.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredProgress {
+    File(ProgressFile),
+    Completed(HashMap<H160, HashSet<H160>>),
+}
 
 #[derive(Debug, Clone)]
 pub struct Progress {
     pub inner: Arc<RwLock<HashMap<H160, HashSet<H160>>>>,
-    pub processing: Arc<RwLock<HashSet<H160>>>,
+    /// Handlers building or sending a bundle, per contract.
+    pub processing: Arc<StdRwLock<HashMap<H160, usize>>>,
     pub latest_block: Arc<RwLock<U64>>,
+    pub gas_paid: Arc<RwLock<GasPaid>>,
+    /// Wallet whose progress `get_progress_for_address` and friends operate on.
+    pub wallet: H160,
+    /// File progress is saved to, kept in memory only if unset.
+    pub path: Option<PathBuf>,
+    /// Held while saving, so concurrent saves do not share the temporary file.
+    save_lock: Arc<Mutex<()>>,
 }
 
 unsafe impl Send for Progress {}
 unsafe impl Sync for Progress {}
 
 impl Progress {
-    pub fn new() -> Self {
+    pub fn new(wallet: H160) -> Self {
         Self {
             inner: Arc::new(RwLock::new(HashMap::new())),
-            processing: Arc::new(RwLock::new(HashSet::new())),
+            processing: Arc::new(StdRwLock::new(HashMap::new())),
             latest_block: Arc::new(RwLock::new(U64::zero())),
+            gas_paid: Arc::new(RwLock::new(HashMap::new())),
+            wallet,
+            path: None,
+            save_lock: Arc::new(Mutex::new(())),
         }
     }
 
-    pub fn read() -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(read_ctf_progress().unwrap())),
-            processing: Arc::new(RwLock::new(HashSet::new())),
-            latest_block: Arc::new(RwLock::new(U64::zero())),
-        }
+    pub fn read(path: &Path, wallet: H160) -> Result<Self, Box<dyn Error>> {
+        let file = read_ctf_progress(path)?;
+        Ok(Self {
+            inner: Arc::new(RwLock::new(file.completed)),
+            gas_paid: Arc::new(RwLock::new(file.gas_paid)),
+            path: Some(path.to_path_buf()),
+            ..Self::new(wallet)
+        })
     }
 
     pub async fn get_latest_block(&self) -> U64 {
@@ -46,50 +80,190 @@ impl Progress {
     }
 
     pub async fn save(&self) {
-        write_ctf_progress(&self.inner.read().await.clone());
+        if let Some(path) = &self.path {
+            let _saving = self.save_lock.lock().await;
+            let file = ProgressFile {
+                completed: self.inner.read().await.clone(),
+                gas_paid: self.gas_paid.read().await.clone(),
+            };
+            if let Err(e) = write_ctf_progress(path, &file) {
+                error!(path = %path.display(), "Could not save progress: {}", e);
+            }
+        }
     }
 
     pub async fn get_is_processing(&self, address: H160) -> bool {
-        self.processing.read().await.get(&address).is_some()
+        self.processing.read().unwrap().contains_key(&address)
     }
 
-    pub async fn set_is_processing(&self, address: H160, is_processing: bool) {
-        if is_processing {
-            self.processing.write().await.insert(address);
-        } else {
-            self.processing.write().await.remove(&address);
+    /// Marks a handler as processing `contract` until the returned guard is dropped.
+    pub fn start_processing(&self, contract: H160) -> ProcessingGuard {
+        *self
+            .processing
+            .write()
+            .unwrap()
+            .entry(contract)
+            .or_default() += 1;
+        ProcessingGuard {
+            processing: self.processing.clone(),
+            contract,
         }
     }
 
+    pub async fn get_gas_paid(&self, contract: H160) -> U256 {
+        self.gas_paid
+            .read()
+            .await
+            .get(&self.wallet)
+            .and_then(|paid| paid.get(&contract))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub async fn add_gas_paid(&self, contract: H160, amount: U256) {
+        let mut gas_paid = self.gas_paid.write().await;
+        let total = gas_paid
+            .entry(self.wallet)
+            .or_default()
+            .entry(contract)
+            .or_default();
+        *total += amount;
+        info!(?contract, paid = %amount, total = %total, "Paid gas");
+        drop(gas_paid);
+        self.save().await;
+    }
+
     pub async fn get_progress_for_address(&self, contract: H160) -> bool {
         self.inner
             .read()
             .await
-            .get(&WALLET.address())
+            .get(&self.wallet)
             .cloned()
             .unwrap_or_default()
             .get(&contract)
             .is_some()
     }
 
+    pub async fn extend_progress(&self, contracts: HashSet<H160>) {
+        self.inner
+            .write()
+            .await
+            .entry(self.wallet)
+            .or_default()
+            .extend(contracts);
+        self.save().await;
+    }
+
     pub async fn add_progress_for_address(&self, contract: H160) {
         self.inner
             .write()
             .await
-            .entry(WALLET.address())
+            .entry(self.wallet)
             .or_default()
             .insert(contract);
         self.save().await;
     }
 }
 
-pub fn read_ctf_progress() -> Result<HashMap<H160, HashSet<H160>>, Box<dyn Error>> {
-    let data = std::fs::read_to_string("ctf_progress.json").unwrap_or_else(|_| "{}".to_string());
-    let progress = serde_json::from_str::<HashMap<H160, HashSet<H160>>>(&data)?;
-    Ok(progress)
+/// Clears a handler's processing flag when it finishes or panics.
+pub struct ProcessingGuard {
+    processing: Arc<StdRwLock<HashMap<H160, usize>>>,
+    contract: H160,
+}
+
+impl Drop for ProcessingGuard {
+    fn drop(&mut self) {
+        let mut processing = self.processing.write().unwrap();
+        if let Some(handlers) = processing.get_mut(&self.contract) {
+            *handlers -= 1;
+            if *handlers == 0 {
+                processing.remove(&self.contract);
+            }
+        }
+    }
+}
+
+pub fn read_ctf_progress(path: &Path) -> Result<ProgressFile, Box<dyn Error>> {
+    let data = std::fs::read_to_string(path).unwrap_or_else(|_| "{}".to_string());
+    Ok(match serde_json::from_str::<StoredProgress>(&data)? {
+        StoredProgress::File(file) => file,
+        StoredProgress::Completed(completed) => ProgressFile {
+            completed,
+            ..Default::default()
+        },
+    })
+}
+
+/// Writes to a temporary file renamed over `path`, so an interrupted write never truncates it.
+pub fn write_ctf_progress(path: &Path, progress: &ProgressFile) -> std::io::Result<()> {
+    let data = serde_json::to_string(progress)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path)
 }
 
-pub fn write_ctf_progress(progress: &HashMap<H160, HashSet<H160>>) {
-    let data = serde_json::to_string(progress).unwrap();
-    std::fs::write("ctf_progress.json", data).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_files_written_before_gas_was_recorded() {
+        let path = std::env::temp_dir().join("mev_share_ctf_legacy_progress.json");
+        let (wallet, contract) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        std::fs::write(&path, format!(r#"{{"{:?}": ["{:?}"]}}"#, wallet, contract)).unwrap();
+
+        let file = read_ctf_progress(&path).unwrap();
+        assert!(file.completed[&wallet].contains(&contract));
+        assert!(file.gas_paid.is_empty());
+    }
+
+    #[tokio::test]
+    async fn persists_gas_paid() {
+        let path = std::env::temp_dir().join("mev_share_ctf_gas_progress.json");
+        let _ = std::fs::remove_file(&path);
+        let (wallet, contract) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+
+        let progress = Progress::read(&path, wallet).unwrap();
+        progress.add_gas_paid(contract, U256::from(21000)).await;
+        progress.add_gas_paid(contract, U256::from(1000)).await;
+
+        let progress = Progress::read(&path, wallet).unwrap();
+        assert_eq!(progress.get_gas_paid(contract).await, U256::from(22000));
+    }
+
+    #[tokio::test]
+    async fn saves_concurrently() {
+        let path = std::env::temp_dir().join("mev_share_ctf_concurrent_progress.json");
+        let _ = std::fs::remove_file(&path);
+        let wallet = H160::from_low_u64_be(1);
+        let progress = Progress::read(&path, wallet).unwrap();
+
+        let saves = (0..16).map(|i| {
+            let progress = progress.clone();
+            tokio::spawn(async move {
+                progress
+                    .add_progress_for_address(H160::from_low_u64_be(100 + i))
+                    .await
+            })
+        });
+        for save in futures_util::future::join_all(saves).await {
+            save.unwrap();
+        }
+
+        let file = read_ctf_progress(&path).unwrap();
+        assert_eq!(file.completed[&wallet].len(), 16);
+    }
+
+    #[tokio::test]
+    async fn clears_processing_when_the_last_handler_finishes() {
+        let progress = Progress::new(H160::from_low_u64_be(1));
+        let contract = H160::from_low_u64_be(2);
+
+        let first = progress.start_processing(contract);
+        let second = progress.start_processing(contract);
+        drop(first);
+        assert!(progress.get_is_processing(contract).await);
+        drop(second);
+        assert!(!progress.get_is_processing(contract).await);
+    }
 }
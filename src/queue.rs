@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use futures_util::future::BoxFuture;
+use tokio::sync::Semaphore;
+use tokio_util::task::TaskTracker;
+use tracing::{warn, Instrument};
+
+use crate::{config::QueueConfig, metrics::Metrics};
+
+/// Takes a job off the pending count when dropped, even if the job panicked.
+struct PendingGuard {
+    pending: Arc<AtomicUsize>,
+    metrics: Metrics,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let pending = self.pending.fetch_sub(1, Ordering::SeqCst) - 1;
+        self.metrics.work_pending.set(pending as i64);
+    }
+}
+
+/// Bounded queue of handler jobs. At most `workers` jobs run at once, and at most the
+/// strategy's limit for jobs of the same strategy. Jobs submitted while `queue_size` jobs are
+/// already waiting or running are dropped.
+pub struct WorkQueue {
+    config: QueueConfig,
+    workers: Arc<Semaphore>,
+    strategies: Mutex<HashMap<&'static str, Arc<Semaphore>>>,
+    pending: Arc<AtomicUsize>,
+    metrics: Metrics,
+    tasks: TaskTracker,
+}
+
+impl WorkQueue {
+    pub fn new(config: QueueConfig, metrics: Metrics, tasks: TaskTracker) -> Self {
+        Self {
+            workers: Arc::new(Semaphore::new(config.workers)),
+            strategies: Mutex::new(HashMap::new()),
+            pending: Arc::new(AtomicUsize::new(0)),
+            config,
+            metrics,
+            tasks,
+        }
+    }
+
+    fn strategy_semaphore(&self, strategy: &'static str) -> Arc<Semaphore> {
+        self.strategies
+            .lock()
+            .unwrap()
+            .entry(strategy)
+            .or_insert_with(|| {
+                let limit = self
+                    .config
+                    .strategy_limits
+                    .get(strategy)
+                    .copied()
+                    .unwrap_or(self.config.strategy_limit);
+                Arc::new(Semaphore::new(limit))
+            })
+            .clone()
+    }
+
+    /// Queues a job, returning false if the queue is full and the job was dropped.
+    pub fn submit(&self, strategy: &'static str, job: BoxFuture<'static, ()>) -> bool {
+        let pending = self.pending.fetch_add(1, Ordering::SeqCst);
+        if pending >= self.config.queue_size {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            self.metrics
+                .work_dropped
+                .with_label_values(&[strategy])
+                .inc();
+            warn!(strategy, pending, "Work queue full, dropping job");
+            return false;
+        }
+        self.metrics.work_pending.set(pending as i64 + 1);
+
+        let workers = self.workers.clone();
+        let strategy_semaphore = self.strategy_semaphore(strategy);
+        let guard = PendingGuard {
+            pending: self.pending.clone(),
+            metrics: self.metrics.clone(),
+        };
+        let metrics = self.metrics.clone();
+        let queued_at = Instant::now();
+        self.tasks.spawn(
+            async move {
+                let _pending = guard;
+                // a burst of one strategy waits on its own limit without holding workers
+                let _strategy = strategy_semaphore.acquire_owned().await;
+                let _worker = workers.acquire_owned().await;
+                metrics
+                    .work_delay
+                    .with_label_values(&[strategy])
+                    .observe(queued_at.elapsed().as_secs_f64());
+                job.await;
+            }
+            .in_current_span(),
+        );
+        true
+    }
+}
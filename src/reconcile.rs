@@ -0,0 +1,44 @@
+use std::{collections::HashSet, error::Error};
+
+use ethers_core::types::{Filter, H256, U64};
+use ethers_providers::Middleware;
+use tracing::info;
+
+use crate::{capture::decode_capture, constants::LOGGER_CONTRACT, context::AppContext};
+
+/// Rebuilds our progress from the logger contract's `FlagCaptured` events between `from` and
+/// `to` (latest block if unset), so captures landed while offline are not retried.
+pub async fn reconcile(
+    ctx: &AppContext,
+    from: U64,
+    to: Option<U64>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let to = match to {
+        Some(to) => to,
+        None => ctx.rpc.get_block_number().await?,
+    };
+    info!(%from, %to, "Reconciling progress");
+
+    let mut captured = HashSet::new();
+    let mut start = from;
+    while start <= to {
+        let end = (start + ctx.config.reconcile_chunk - 1u64).min(to);
+        let filter = Filter::new()
+            .address(*LOGGER_CONTRACT)
+            .event("FlagCaptured(address,address)")
+            .topic1(H256::from(ctx.address()))
+            .from_block(start)
+            .to_block(end);
+        for log in ctx.rpc.get_logs(&filter).await? {
+            let capture = decode_capture(&log)?;
+            if capture.capturer == ctx.address() {
+                captured.insert(capture.challenge);
+            }
+        }
+        start = end + 1u64;
+    }
+
+    info!(count = captured.len(), "Found captured challenges on chain");
+    ctx.progress.extend_progress(captured).await;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Lines, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers_core::types::{Block, Transaction};
+use mev_share_sse::Event;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Record {
+    Event(Event),
+    Block(Block<Transaction>),
+}
+
+/// One line of a capture file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureEntry {
+    /// Unix timestamp in milliseconds at which the record was received.
+    pub received_at: u64,
+    pub record: Record,
+}
+
+/// Appends SSE events and blocks to a capture file.
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        info!(path = %path.display(), "Recording events and blocks");
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record_event(&self, event: &Event) {
+        self.record(Record::Event(event.clone()));
+    }
+
+    pub fn record_block(&self, block: &Block<Transaction>) {
+        self.record(Record::Block(block.clone()));
+    }
+
+    fn record(&self, record: Record) {
+        let entry = CaptureEntry {
+            received_at: now_millis(),
+            record,
+        };
+        match serde_json::to_string(&entry) {
+            Ok(data) => {
+                if let Err(e) = writeln!(self.file.lock().unwrap(), "{}", data) {
+                    error!("Error writing capture entry: {:?}", e);
+                }
+            }
+            Err(e) => error!("Error serializing capture entry: {:?}", e),
+        }
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Reads back a capture file, yielding records at their original pace divided by `speed`.
+/// A `speed` of zero replays as fast as possible.
+pub struct ReplaySource {
+    lines: Lines<BufReader<File>>,
+    speed: f64,
+    started: Option<(Instant, u64)>,
+}
+
+impl ReplaySource {
+    pub fn open(path: &Path, speed: f64) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            lines: BufReader::new(File::open(path)?).lines(),
+            speed,
+            started: None,
+        })
+    }
+
+    pub async fn next(&mut self) -> Option<Result<CaptureEntry, Box<dyn Error>>> {
+        let line = loop {
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => break line,
+                Err(e) => return Some(Err(e.into())),
+            }
+        };
+        let entry = match serde_json::from_str::<CaptureEntry>(&line) {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e.into())),
+        };
+
+        let (start, first) = *self
+            .started
+            .get_or_insert((Instant::now(), entry.received_at));
+        if self.speed > 0.0 {
+            let offset = entry.received_at.saturating_sub(first) as f64 / self.speed;
+            tokio::time::sleep_until(start + Duration::from_millis(offset as u64)).await;
+        }
+        Some(Ok(entry))
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    future::Future,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use ethers_core::rand::{thread_rng, Rng};
+use futures_util::future::BoxFuture;
+use jsonrpsee::{core::Error as RpcError, http_client::transport};
+use mev_share_rpc_api::{MevApiClient, SendBundleRequest, SendBundleResponse};
+use serde::Deserialize;
+use tokio::{sync::Mutex, time::Instant};
+use tower::{retry::Policy, timeout::error::Elapsed, Layer, Service};
+use tracing::warn;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Rate limit, timeout and retry settings of one relay.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RelayLimits {
+    /// Bundles sent per second once the burst is used up.
+    pub rate: f64,
+    /// Bundles that can be sent back to back.
+    pub burst: u32,
+    /// Timeout of a single `mev_sendBundle` attempt.
+    pub timeout_ms: u64,
+    /// Retries after the first attempt, for timeouts, connection errors, 429s and 5xx only.
+    pub max_retries: usize,
+    /// Upper bound of the first retry's jittered delay, doubled for every retry after it.
+    pub backoff_ms: u64,
+}
+
+impl Default for RelayLimits {
+    fn default() -> Self {
+        Self {
+            rate: 5.0,
+            burst: 10,
+            timeout_ms: 2000,
+            max_retries: 3,
+            backoff_ms: 100,
+        }
+    }
+}
+
+/// Reads the limits of every relay, keyed by URL, from a JSON file.
+pub fn read_relay_limits(path: &Path) -> Result<HashMap<String, RelayLimits>, Box<dyn Error>> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read relay limits {}: {}", path.display(), e))?;
+    let limits = serde_json::from_str::<HashMap<String, RelayLimits>>(&data)?;
+    for (url, limit) in &limits {
+        // any of these would stall or panic every send to the relay
+        if limit.rate <= 0.0 || limit.burst == 0 || limit.timeout_ms == 0 {
+            return Err(format!(
+                "Relay limits of {} need a positive rate, burst and timeout_ms",
+                url
+            )
+            .into());
+        }
+    }
+    Ok(limits)
+}
+
+/// `mev_sendBundle` on a jsonrpsee client as a tower service.
+#[derive(Clone)]
+pub struct SendBundleService {
+    pub client: Arc<dyn MevApiClient + Send + Sync>,
+}
+
+impl Service<SendBundleRequest> for SendBundleService {
+    type Response = SendBundleResponse;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<SendBundleResponse, BoxError>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, bundle: SendBundleRequest) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move { Ok(client.send_bundle(bundle).await?) })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    /// Adds the tokens earned since the last refill and takes one, or returns how long until
+    /// one is available.
+    fn take(&mut self, now: Instant, rate: f64, burst: f64) -> Result<(), Duration> {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+/// Token bucket shared by every clone of the service it wraps.
+#[derive(Clone)]
+pub struct TokenBucketLayer {
+    rate: f64,
+    burst: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl TokenBucketLayer {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: burst as f64,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = match self
+                .bucket
+                .lock()
+                .await
+                .take(Instant::now(), self.rate, self.burst)
+            {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl<S> Layer<S> for TokenBucketLayer {
+    type Service = TokenBucket<S>;
+
+    fn layer(&self, inner: S) -> TokenBucket<S> {
+        TokenBucket {
+            inner,
+            limit: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenBucket<S> {
+    inner: S,
+    limit: TokenBucketLayer,
+}
+
+impl<S, R> Service<R> for TokenBucket<S>
+where
+    S: Service<R> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+    R: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<S::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let limit = self.limit.clone();
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            limit.acquire().await;
+            inner.call(request).await.map_err(Into::into)
+        })
+    }
+}
+
+/// Retries timeouts, connection errors, 429s and 5xx with full jitter exponential backoff. Errors
+/// returned by the relay itself, e.g. an invalid bundle, are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub retries_left: usize,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(limits: &RelayLimits) -> Self {
+        Self {
+            retries_left: limits.max_retries,
+            backoff: Duration::from_millis(limits.backoff_ms),
+        }
+    }
+}
+
+pub fn is_retryable(error: &BoxError) -> bool {
+    if error.is::<Elapsed>() {
+        return true;
+    }
+    match error.downcast_ref::<RpcError>() {
+        Some(RpcError::RequestTimeout) => true,
+        Some(RpcError::Transport(e)) => match e.downcast_ref::<transport::Error>() {
+            Some(transport::Error::Rejected { status_code }) => {
+                *status_code == 429 || *status_code >= 500
+            }
+            _ => true,
+        },
+        _ => false,
+    }
+}
+
+impl<Res> Policy<SendBundleRequest, Res, BoxError> for RetryPolicy {
+    type Future = Pin<Box<dyn Future<Output = Self> + Send>>;
+
+    fn retry(
+        &self,
+        _: &SendBundleRequest,
+        result: Result<&Res, &BoxError>,
+    ) -> Option<Self::Future> {
+        let error = result.err()?;
+        if self.retries_left == 0 || !is_retryable(error) {
+            return None;
+        }
+        let delay =
+            Duration::from_millis(thread_rng().gen_range(0..=self.backoff.as_millis() as u64));
+        warn!(
+            retries_left = self.retries_left,
+            delay_ms = delay.as_millis() as u64,
+            "Retrying bundle after {:?}",
+            error
+        );
+        let next = Self {
+            retries_left: self.retries_left - 1,
+            backoff: self.backoff * 2,
+        };
+        Some(Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            next
+        }))
+    }
+
+    fn clone_request(&self, bundle: &SendBundleRequest) -> Option<SendBundleRequest> {
+        Some(bundle.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpsee::types::{error::CallError, ErrorObject};
+
+    use super::*;
+
+    fn rejected(status_code: u16) -> BoxError {
+        Box::new(RpcError::Transport(
+            transport::Error::Rejected { status_code }.into(),
+        ))
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        assert!(is_retryable(&(Box::new(Elapsed::new()) as BoxError)));
+        assert!(is_retryable(
+            &(Box::new(RpcError::RequestTimeout) as BoxError)
+        ));
+        assert!(is_retryable(&rejected(429)));
+        assert!(is_retryable(&rejected(502)));
+        assert!(!is_retryable(&rejected(400)));
+
+        let call = RpcError::Call(CallError::Custom(ErrorObject::owned(
+            -32000,
+            "invalid bundle",
+            None::<()>,
+        )));
+        assert!(!is_retryable(&(Box::new(call) as BoxError)));
+    }
+
+    #[test]
+    fn bucket_refills_at_rate_up_to_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            tokens: 2.0,
+            refilled_at: start,
+        };
+        assert!(bucket.take(start, 10.0, 2.0).is_ok());
+        assert!(bucket.take(start, 10.0, 2.0).is_ok());
+        let wait = bucket.take(start, 10.0, 2.0).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        let later = start + Duration::from_millis(100);
+        assert!(bucket.take(later, 10.0, 2.0).is_ok());
+        assert!(bucket.take(later, 10.0, 2.0).is_err());
+
+        // idle time never adds more than the burst
+        let idle = later + Duration::from_secs(60);
+        assert!(bucket.take(idle, 10.0, 2.0).is_ok());
+        assert!(bucket.take(idle, 10.0, 2.0).is_ok());
+        assert!(bucket.take(idle, 10.0, 2.0).is_err());
+    }
+}
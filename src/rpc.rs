@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    fmt::Debug,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use ethers_core::types::U64;
+use ethers_providers::{Http, HttpClientError, JsonRpcClient, RpcError};
+use futures_util::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Endpoints more than this many blocks behind the highest one are considered unhealthy.
+const MAX_BLOCK_LAG: u64 = 3;
+/// Weight of the newest sample in an endpoint's latency average.
+const LATENCY_WEIGHT: f64 = 0.3;
+
+#[derive(Debug)]
+struct Health {
+    healthy: bool,
+    latency: Duration,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    client: Http,
+    health: RwLock<Health>,
+}
+
+impl Endpoint {
+    fn observe(&self, latency: Duration) {
+        let mut health = self.health.write().unwrap();
+        health.latency =
+            health.latency.mul_f64(1.0 - LATENCY_WEIGHT) + latency.mul_f64(LATENCY_WEIGHT);
+    }
+
+    fn set_healthy(&self, healthy: bool, reason: &str) {
+        let mut health = self.health.write().unwrap();
+        if health.healthy != healthy {
+            if healthy {
+                info!(url = %self.url, "RPC endpoint is back up");
+            } else {
+                warn!(url = %self.url, reason, "RPC endpoint is down, failing over");
+            }
+            health.healthy = healthy;
+        }
+    }
+}
+
+/// A JSON-RPC transport over several HTTP endpoints. Requests go to the healthy endpoint with
+/// the lowest latency, and to the next ones when it cannot be reached.
+#[derive(Debug, Clone)]
+pub struct FailoverClient {
+    endpoints: Arc<Vec<Endpoint>>,
+}
+
+impl FailoverClient {
+    pub fn new(urls: &[String]) -> Result<Self, Box<dyn Error>> {
+        if urls.is_empty() {
+            return Err("No RPC URL configured".into());
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| -> Result<Endpoint, Box<dyn Error>> {
+                Ok(Endpoint {
+                    url: url.clone(),
+                    client: Http::from_str(url)?,
+                    health: RwLock::new(Health {
+                        healthy: true,
+                        latency: Duration::ZERO,
+                    }),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+        })
+    }
+
+    /// Endpoints in the order requests try them: healthy ones by latency, then unhealthy ones as
+    /// a last resort.
+    fn ranked(&self) -> Vec<&Endpoint> {
+        let mut endpoints = self.endpoints.iter().collect::<Vec<_>>();
+        endpoints.sort_by_key(|e| {
+            let health = e.health.read().unwrap();
+            (!health.healthy, health.latency)
+        });
+        endpoints
+    }
+
+    /// Polls `eth_blockNumber` on every endpoint each `interval`, marking those that fail or lag
+    /// behind as unhealthy and the others as healthy again.
+    pub fn spawn_health_checks(&self, interval: Duration) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                client.check_health().await;
+            }
+        })
+    }
+
+    async fn check_health(&self) {
+        let blocks = join_all(self.endpoints.iter().map(|e| async move {
+            let started = Instant::now();
+            match e.client.request::<_, U64>("eth_blockNumber", ()).await {
+                Ok(block) => {
+                    e.observe(started.elapsed());
+                    Some(block)
+                }
+                Err(err) => {
+                    e.set_healthy(false, &err.to_string());
+                    None
+                }
+            }
+        }))
+        .await;
+
+        let highest = blocks.iter().flatten().max().copied().unwrap_or_default();
+        for (endpoint, block) in self.endpoints.iter().zip(blocks) {
+            match block {
+                Some(block) if block + MAX_BLOCK_LAG < highest => {
+                    endpoint.set_healthy(false, &format!("{} blocks behind", highest - block))
+                }
+                Some(_) => endpoint.set_healthy(true, ""),
+                None => {}
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverClient {
+    type Error = HttpClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, HttpClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // serialized once, as it may be sent to every endpoint
+        let params = serde_json::to_value(params).map_err(|err| HttpClientError::SerdeJson {
+            err,
+            text: String::new(),
+        })?;
+
+        let mut last_err = None;
+        for endpoint in self.ranked() {
+            let started = Instant::now();
+            match endpoint.client.request(method, &params).await {
+                Ok(res) => {
+                    endpoint.observe(started.elapsed());
+                    return Ok(res);
+                }
+                // the node answered, the others would most likely answer the same
+                Err(err) if err.is_error_response() => return Err(err),
+                Err(err) => {
+                    debug!(url = %endpoint.url, method, "RPC request failed: {:?}", err);
+                    endpoint.set_healthy(false, &err.to_string());
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.expect("FailoverClient has at least one endpoint"))
+    }
+}
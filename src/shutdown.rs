@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::context::AppContext;
+
+/// Resolves on Ctrl-C, or SIGTERM on unix.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Could not listen for Ctrl-C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Could not listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl-C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Waits up to `timeout` for the handlers still running, then flushes progress to disk and logs
+/// the work left behind. Intake must already be stopped.
+pub async fn drain(ctx: &AppContext, timeout: Duration) {
+    ctx.tasks.close();
+    info!(
+        tasks = ctx.tasks.len(),
+        timeout_secs = timeout.as_secs(),
+        "Waiting for in-flight handlers"
+    );
+    if tokio::time::timeout(timeout, ctx.tasks.wait())
+        .await
+        .is_err()
+    {
+        let processing = ctx
+            .progress
+            .processing
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        warn!(
+            tasks = ctx.tasks.len(),
+            contracts = ?processing,
+            "Abandoning handlers still running after the shutdown timeout"
+        );
+    }
+
+    ctx.progress.save().await;
+    let in_flight = ctx.admin.in_flight.read().await;
+    if !in_flight.is_empty() {
+        info!(bundles = ?in_flight.keys().collect::<Vec<_>>(), "Bundles still in flight at shutdown");
+    }
+    info!("Progress flushed, shut down");
+}
@@ -1,20 +1,21 @@
 use std::{error::Error, str::FromStr};
 
 use ethers_core::types::{Bytes, Eip1559TransactionRequest};
-use ethers_signers::Signer;
+use ethers_signers::{LocalWallet, Signer};
 
-use crate::constants::{GAS_LIMIT, GWEI, MAX_GAS_PRICE, MAX_PRIORITY_FEE, TIP, WALLET};
+use crate::constants::{GAS_LIMIT, GWEI, MAX_GAS_PRICE, MAX_PRIORITY_FEE, TIP};
 
 pub async fn sign_transaction(
+    wallet: &LocalWallet,
     tx: Eip1559TransactionRequest,
 ) -> Result<Bytes, Box<dyn Error + Sync + Send>> {
     let tx = tx
-        .from(WALLET.address())
-        .chain_id(5)
+        .from(wallet.address())
+        .chain_id(wallet.chain_id())
         .max_priority_fee_per_gas(MAX_PRIORITY_FEE * GWEI + TIP)
         .max_fee_per_gas(MAX_GAS_PRICE * GWEI + TIP)
         .gas(GAS_LIMIT);
-    let signature = WALLET.sign_transaction(&tx.clone().into()).await?;
+    let signature = wallet.sign_transaction(&tx.clone().into()).await?;
     let bytes = tx.rlp_signed(&signature);
     Ok(Bytes::from_str(&format!(
         "0x02{}",
@@ -0,0 +1,154 @@
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use ethers_core::{types::H256, utils::keccak256};
+use mev_share_rpc_api::{SendBundleRequest, SendBundleResponse};
+use tracing::info;
+
+use crate::client::Client;
+
+/// Anything bundles can be sent to.
+#[async_trait]
+pub trait BundleSink: Send + Sync {
+    /// Name of the relay, used in logs.
+    fn name(&self) -> &str;
+
+    async fn send_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>>;
+}
+
+#[async_trait]
+impl BundleSink for Client {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    async fn send_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>> {
+        Client::send_bundle(self, bundle).await
+    }
+}
+
+fn bundle_hash(bundle: &SendBundleRequest) -> Result<H256, Box<dyn Error + Send + Sync>> {
+    Ok(H256(keccak256(serde_json::to_vec(bundle)?)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockBehaviour {
+    /// Respond with a bundle hash.
+    Accept,
+    /// Respond with an error.
+    Reject,
+    /// Respond with a bundle hash and mark the bundle as included.
+    Include,
+}
+
+/// An in-process relay recording every bundle it receives.
+pub struct MockRelay {
+    behaviour: Mutex<MockBehaviour>,
+    bundles: Mutex<Vec<SendBundleRequest>>,
+    included: Mutex<Vec<SendBundleRequest>>,
+}
+
+impl MockRelay {
+    pub fn new(behaviour: MockBehaviour) -> Self {
+        Self {
+            behaviour: Mutex::new(behaviour),
+            bundles: Mutex::new(vec![]),
+            included: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn set_behaviour(&self, behaviour: MockBehaviour) {
+        *self.behaviour.lock().unwrap() = behaviour;
+    }
+
+    /// Every bundle received so far, rejected ones included.
+    pub fn bundles(&self) -> Vec<SendBundleRequest> {
+        self.bundles.lock().unwrap().clone()
+    }
+
+    pub fn included(&self) -> Vec<SendBundleRequest> {
+        self.included.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl BundleSink for MockRelay {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn send_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>> {
+        self.bundles.lock().unwrap().push(bundle.clone());
+        let behaviour = *self.behaviour.lock().unwrap();
+        match behaviour {
+            MockBehaviour::Reject => Err("Bundle rejected by mock relay".into()),
+            MockBehaviour::Accept => Ok(SendBundleResponse {
+                bundle_hash: bundle_hash(&bundle)?,
+            }),
+            MockBehaviour::Include => {
+                let bundle_hash = bundle_hash(&bundle)?;
+                self.included.lock().unwrap().push(bundle);
+                Ok(SendBundleResponse { bundle_hash })
+            }
+        }
+    }
+}
+
+/// Writes bundles out instead of sending them, as newline delimited JSON to a file or pretty
+/// printed to stdout.
+pub struct FileSink {
+    file: Option<Mutex<File>>,
+}
+
+impl FileSink {
+    pub fn new(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let file = match path {
+            Some(path) => {
+                info!(path = %path.display(), "Dry run: writing bundles to file");
+                Some(Mutex::new(
+                    OpenOptions::new().create(true).append(true).open(path)?,
+                ))
+            }
+            None => {
+                info!("Dry run: printing bundles instead of sending them");
+                None
+            }
+        };
+        Ok(Self { file })
+    }
+}
+
+#[async_trait]
+impl BundleSink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn send_bundle(
+        &self,
+        bundle: SendBundleRequest,
+    ) -> Result<SendBundleResponse, Box<dyn Error + Send + Sync>> {
+        match &self.file {
+            Some(file) => writeln!(file.lock().unwrap(), "{}", serde_json::to_string(&bundle)?)?,
+            None => println!("Built bundle: {}", serde_json::to_string_pretty(&bundle)?),
+        }
+        Ok(SendBundleResponse {
+            bundle_hash: bundle_hash(&bundle)?,
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use std::{error::Error, path::PathBuf, sync::Mutex};
+
+use async_trait::async_trait;
+use futures_util::{
+    future::ready,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use mev_share_sse::{Event, EventClient, EventHistoryParams};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::{error, info};
+
+use crate::recorder::{CaptureEntry, Record, ReplaySource};
+
+pub type EventStream = BoxStream<'static, Event>;
+
+/// Anything producing MEV-Share events for the executor.
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    async fn events(&self) -> Result<EventStream, Box<dyn Error + Send + Sync>>;
+}
+
+/// The live MEV-Share SSE endpoint. The stream ends on the first SSE error.
+pub struct SseSource {
+    pub client: EventClient,
+    pub endpoint: String,
+}
+
+impl SseSource {
+    pub fn new(client: EventClient, endpoint: &str) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSource for SseSource {
+    async fn events(&self) -> Result<EventStream, Box<dyn Error + Send + Sync>> {
+        let stream = self.client.events(&self.endpoint).await?;
+        info!(endpoint = %stream.endpoint(), "Subscribed to SSE");
+        Ok(stream
+            .take_while(|event| ready(event.is_ok()))
+            .filter_map(|event| ready(event.ok()))
+            .boxed())
+    }
+}
+
+/// The events of a capture file, replayed at their original pace divided by `speed`.
+pub struct FileSource {
+    pub path: PathBuf,
+    pub speed: f64,
+}
+
+#[async_trait]
+impl EventSource for FileSource {
+    async fn events(&self) -> Result<EventStream, Box<dyn Error + Send + Sync>> {
+        let source = ReplaySource::open(&self.path, self.speed)
+            .map_err(|e| format!("Could not open {}: {}", self.path.display(), e))?;
+        Ok(stream::unfold(source, |mut source| async move {
+            loop {
+                let entry = source.next().await;
+                match entry {
+                    Some(Ok(CaptureEntry {
+                        record: Record::Event(event),
+                        ..
+                    })) => return Some((event, source)),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        error!("Error reading capture entry: {:?}", e);
+                        return None;
+                    }
+                    None => return None,
+                }
+            }
+        })
+        .boxed())
+    }
+}
+
+/// Events pushed through an in-memory channel, for tests and embedding.
+pub struct ChannelSource {
+    receiver: Mutex<Option<UnboundedReceiver<Event>>>,
+}
+
+impl ChannelSource {
+    pub fn new() -> (UnboundedSender<Event>, Self) {
+        let (sender, receiver) = unbounded_channel();
+        (
+            sender,
+            Self {
+                receiver: Mutex::new(Some(receiver)),
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl EventSource for ChannelSource {
+    async fn events(&self) -> Result<EventStream, Box<dyn Error + Send + Sync>> {
+        let receiver = self
+            .receiver
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or("Channel source already consumed")?;
+        Ok(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|event| (event, receiver))
+        })
+        .boxed())
+    }
+}
+
+/// Past events fetched from the MEV-Share history API.
+pub struct HistorySource {
+    pub client: EventClient,
+    pub endpoint: String,
+    pub params: EventHistoryParams,
+}
+
+#[async_trait]
+impl EventSource for HistorySource {
+    async fn events(&self) -> Result<EventStream, Box<dyn Error + Send + Sync>> {
+        let history = self
+            .client
+            .event_history(&self.endpoint, self.params.clone())
+            .await?;
+        info!(count = history.len(), "Fetched historical events");
+        Ok(stream::iter(history.into_iter().map(|entry| Event {
+            hash: entry.hint.hash,
+            transactions: entry.hint.txs.unwrap_or_default(),
+            logs: entry.hint.logs.unwrap_or_default(),
+        }))
+        .boxed())
+    }
+}
@@ -0,0 +1,66 @@
+use std::{sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use mev_share_ctf_rs::{
+    mock_sse::{ctf_script, MockSseServer},
+    sink::{MockBehaviour, MockRelay},
+    source::SseSource,
+    AppContext, Config, EventSource, Executor,
+};
+use mev_share_sse::EventClient;
+
+#[tokio::test]
+async fn sse_script_matches_every_strategy() {
+    let script = ctf_script();
+    let server = MockSseServer::start(script.clone(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    let path = std::env::temp_dir().join("mev_share_ctf_mock_sse_progress.json");
+    let _ = std::fs::remove_file(&path);
+    let ctx = Arc::new(
+        AppContext::new(
+            Config::for_tests(path),
+            Arc::new(MockRelay::new(MockBehaviour::Reject)),
+        )
+        .unwrap(),
+    );
+
+    let source = SseSource::new(EventClient::default(), &server.url);
+    let events = tokio::time::timeout(
+        Duration::from_secs(10),
+        source
+            .events()
+            .await
+            .unwrap()
+            .take(script.len())
+            .collect::<Vec<_>>(),
+    )
+    .await
+    .expect("Mock SSE server did not send the whole script");
+
+    let matched = events
+        .iter()
+        .map(|event| {
+            Executor::match_event(&ctx, event)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        matched,
+        [
+            "simple_contract_1",
+            "simple_contract_2",
+            "simple_contract_3",
+            "simple_contract_4",
+            "simple_contract_triple",
+            "magic_contract_1",
+            "magic_contract_2",
+            "magic_contract_3",
+            "new_contract_addr",
+            "new_contract_salt",
+        ]
+        .map(|name| vec![name])
+    );
+}